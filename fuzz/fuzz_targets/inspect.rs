@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::carve;
+use pngme::chunk::Chunk;
+use pngme::inspect::Inspection;
+use pngme::repair;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Chunk::try_from(data);
+    let _ = carve::carve(data);
+    let _ = repair::repair(data, false);
+    if let Ok(inspection) = Inspection::scan(data) {
+        let _ = inspection.render(false);
+    }
+});
@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::apng::Animation;
+use pngme::color::ColorSpace;
+use pngme::palette::PaletteInfo;
+use pngme::png::Png;
+use pngme::{json, signature, validate};
+
+// Everything print, validate and the JSON output run on a parsed file
+fuzz_target!(|data: &[u8]| {
+    let Ok(png) = Png::parse(data, 1 << 24) else {
+        return;
+    };
+    let _ = validate::validate(&png);
+    let _ = json::png(&png).to_string();
+    let _ = PaletteInfo::from_png(&png).map(|info| info.to_string());
+    let _ = ColorSpace::from_png(&png).to_string();
+    let _ = signature::signatures(&png);
+    if let Ok(animation) = Animation::from_png(&png) {
+        for idx in 0..animation.frames().len() {
+            let _ = animation.frame_png(idx);
+        }
+    }
+    for chunk in png.chunks() {
+        if let Some(info) = chunk.chunk_type().known_info() {
+            let _ = info.pretty_print(chunk);
+        }
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::attachment::Attachment;
+use pngme::{compression, split};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = compression::decompress(data);
+    let _ = Attachment::try_from(data);
+    let _ = split::reassemble(&[data]);
+    let (first, second) = data.split_at(data.len() / 2);
+    let _ = split::reassemble(&[first, second]);
+});
@@ -0,0 +1,161 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitByteStr, LitStr};
+
+// Fields are written in declaration order: integers big-endian, bool as one byte,
+// [u8; N] as-is, String and Vec<u8> behind a u32 length prefix. The last field can be
+// marked #[png_chunk(rest)] to take the remainder of the chunk without a prefix.
+#[proc_macro_derive(PngChunk, attributes(png_chunk))]
+pub fn derive_png_chunk(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Field {
+    binding: syn::Ident,
+    member: syn::Member,
+    rest: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let chunk_type = chunk_type(input)?;
+    let letters = LitByteStr::new(chunk_type.value().as_bytes(), chunk_type.span());
+    let fields = match &input.data {
+        Data::Struct(data) => fields(&data.fields)?,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "PngChunk can only be derived for structs",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let writes = fields.iter().map(|field| {
+        let member = &field.member;
+        if field.rest {
+            quote!(::pngme::field::RestField::write_rest(&self.#member, &mut data);)
+        } else {
+            quote!(::pngme::field::ChunkField::write_to(&self.#member, &mut data);)
+        }
+    });
+    let reads = fields.iter().map(|field| {
+        let binding = &field.binding;
+        if field.rest {
+            quote!(let #binding = ::pngme::field::RestField::read_rest(&mut reader)?;)
+        } else {
+            quote!(let #binding = ::pngme::field::ChunkField::read_from(&mut reader)?;)
+        }
+    });
+    let members = fields.iter().map(|field| {
+        let member = &field.member;
+        let binding = &field.binding;
+        quote!(#member: #binding)
+    });
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub const CHUNK_TYPE: &'static str = #chunk_type;
+
+            pub fn to_chunk(&self) -> ::pngme::chunk::Chunk {
+                let mut data = ::std::vec::Vec::new();
+                #(#writes)*
+                ::pngme::chunk::Chunk::new(
+                    ::pngme::chunk_type::ChunkType::from_letters(*#letters),
+                    data,
+                )
+            }
+        }
+
+        impl #impl_generics ::std::convert::TryFrom<&::pngme::chunk::Chunk> for #name #ty_generics #where_clause {
+            type Error = ::pngme::Error;
+
+            fn try_from(chunk: &::pngme::chunk::Chunk) -> ::std::result::Result<Self, Self::Error> {
+                chunk.expect_type(Self::CHUNK_TYPE)?;
+                let mut reader = ::pngme::field::FieldReader::new(Self::CHUNK_TYPE, chunk.data());
+                #(#reads)*
+                reader.finish()?;
+                Ok(Self { #(#members),* })
+            }
+        }
+    })
+}
+
+fn chunk_type(input: &DeriveInput) -> syn::Result<LitStr> {
+    let mut chunk_type = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("png_chunk"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("chunk_type") {
+                chunk_type = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `chunk_type = \"...\"`"))
+            }
+        })?;
+    }
+    let chunk_type = chunk_type.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing #[png_chunk(chunk_type = \"...\")] attribute",
+        )
+    })?;
+    let value = chunk_type.value();
+    if value.len() != 4 || !value.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+        return Err(syn::Error::new_spanned(
+            &chunk_type,
+            "chunk type must be four ASCII letters",
+        ));
+    }
+    if !value.as_bytes()[2].is_ascii_uppercase() {
+        return Err(syn::Error::new_spanned(
+            &chunk_type,
+            "the third letter of a chunk type is reserved and must be uppercase",
+        ));
+    }
+    Ok(chunk_type)
+}
+
+fn fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    let mut parsed = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let mut rest = false;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("png_chunk"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rest") {
+                    rest = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rest`"))
+                }
+            })?;
+        }
+        if rest && index + 1 != fields.len() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only the last field can take the rest of the chunk",
+            ));
+        }
+        let (binding, member) = match &field.ident {
+            Some(ident) => (ident.clone(), syn::Member::Named(ident.clone())),
+            None => (format_ident!("field_{index}"), syn::Member::from(index)),
+        };
+        parsed.push(Field {
+            binding,
+            member,
+            rest,
+        });
+    }
+    Ok(parsed)
+}
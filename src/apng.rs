@@ -0,0 +1,655 @@
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::ihdr::ImageHeader;
+use crate::png::Png;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AnimationControl {
+    num_frames: u32,
+    num_plays: u32,
+}
+
+impl AnimationControl {
+    pub const CHUNK_TYPE: &'static str = "acTL";
+
+    pub fn new(num_frames: u32, num_plays: u32) -> crate::Result<Self> {
+        if num_frames == 0 {
+            return Err("acTL must declare at least one frame".into());
+        }
+        Ok(Self {
+            num_frames,
+            num_plays,
+        })
+    }
+
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    // Zero means the animation loops forever
+    pub fn num_plays(&self) -> u32 {
+        self.num_plays
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = self
+            .num_frames
+            .to_be_bytes()
+            .iter()
+            .chain(self.num_plays.to_be_bytes().iter())
+            .copied()
+            .collect();
+        Chunk::new(ChunkType::ACTL, data)
+    }
+}
+
+impl TryFrom<&Chunk> for AnimationControl {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_min_len(8)?;
+        Self::new(
+            chunk::read_u32(chunk.data(), 0),
+            chunk::read_u32(chunk.data(), 4),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum DisposeOp {
+    None,
+    Background,
+    Previous,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum BlendOp {
+    Source,
+    Over,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FrameControl {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp,
+}
+
+impl FrameControl {
+    pub const CHUNK_TYPE: &'static str = "fcTL";
+
+    // A zero denominator is treated as 100, i.e. the numerator is in centiseconds
+    pub fn delay(&self) -> f64 {
+        let den = if self.delay_den == 0 {
+            100
+        } else {
+            self.delay_den
+        };
+        self.delay_num as f64 / den as f64
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Vec::with_capacity(26);
+        for value in [
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+        ] {
+            data.extend(value.to_be_bytes());
+        }
+        data.extend(self.delay_num.to_be_bytes());
+        data.extend(self.delay_den.to_be_bytes());
+        data.push(self.dispose_op as u8);
+        data.push(self.blend_op as u8);
+        Chunk::new(ChunkType::FCTL, data)
+    }
+}
+
+impl TryFrom<&Chunk> for FrameControl {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_min_len(26)?;
+        let data = chunk.data();
+        let dispose_op = match data[24] {
+            0 => DisposeOp::None,
+            1 => DisposeOp::Background,
+            2 => DisposeOp::Previous,
+            other => return Err(format!("Invalid fcTL dispose_op: {other}").into()),
+        };
+        let blend_op = match data[25] {
+            0 => BlendOp::Source,
+            1 => BlendOp::Over,
+            other => return Err(format!("Invalid fcTL blend_op: {other}").into()),
+        };
+        let control = Self {
+            sequence_number: chunk::read_u32(data, 0),
+            width: chunk::read_u32(data, 4),
+            height: chunk::read_u32(data, 8),
+            x_offset: chunk::read_u32(data, 12),
+            y_offset: chunk::read_u32(data, 16),
+            delay_num: chunk::read_u16(data, 20),
+            delay_den: chunk::read_u16(data, 22),
+            dispose_op,
+            blend_op,
+        };
+        if control.width == 0 || control.height == 0 {
+            return Err(
+                format!("fcTL {} has an empty frame region", control.sequence_number).into(),
+            );
+        }
+        Ok(control)
+    }
+}
+
+pub struct FrameData;
+
+impl FrameData {
+    pub const CHUNK_TYPE: &'static str = "fdAT";
+
+    pub fn to_chunk(sequence_number: u32, data: &[u8]) -> Chunk {
+        let data = sequence_number
+            .to_be_bytes()
+            .iter()
+            .chain(data.iter())
+            .copied()
+            .collect();
+        Chunk::new(ChunkType::FDAT, data)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    control: FrameControl,
+    // Compressed image data, one entry per IDAT/fdAT chunk
+    data: Vec<Vec<u8>>,
+}
+
+impl Frame {
+    pub fn control(&self) -> &FrameControl {
+        &self.control
+    }
+
+    pub fn data(&self) -> &[Vec<u8>] {
+        &self.data
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FrameSettings {
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp,
+}
+
+impl FrameSettings {
+    pub fn from_delay_ms(delay_ms: u16) -> Self {
+        Self {
+            delay_num: delay_ms,
+            delay_den: 1000,
+            x_offset: 0,
+            y_offset: 0,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Animation {
+    header: ImageHeader,
+    control: AnimationControl,
+    frames: Vec<Frame>,
+    default_image_is_frame: bool,
+    shared_chunks: Vec<Chunk>,
+    issues: Vec<String>,
+}
+
+impl Animation {
+    pub const CHUNK_TYPES: [&'static str; 3] = [
+        AnimationControl::CHUNK_TYPE,
+        FrameControl::CHUNK_TYPE,
+        FrameData::CHUNK_TYPE,
+    ];
+
+    pub fn is_animated(png: &Png) -> bool {
+        png.chunk_by_type(AnimationControl::CHUNK_TYPE).is_some()
+    }
+
+    pub fn from_png(png: &Png) -> crate::Result<Self> {
+        let header = ImageHeader::from_png(png)?;
+        let control = png
+            .chunk_by_type(AnimationControl::CHUNK_TYPE)
+            .ok_or("Not an animated PNG: acTL chunk missing")?;
+        let control = AnimationControl::try_from(control)?;
+
+        let mut animation = Self {
+            header,
+            control,
+            frames: Vec::new(),
+            default_image_is_frame: false,
+            shared_chunks: Vec::new(),
+            issues: Vec::new(),
+        };
+        let mut seen_image_data = false;
+        let mut next_sequence_number = 0;
+
+        for chunk in png.chunks().iter().skip(1) {
+            match chunk.chunk_type().to_string().as_str() {
+                FrameControl::CHUNK_TYPE => {
+                    let frame_control = FrameControl::try_from(chunk)?;
+                    animation
+                        .check_sequence(frame_control.sequence_number, &mut next_sequence_number);
+                    animation.check_region(&frame_control);
+                    if !seen_image_data {
+                        animation.default_image_is_frame = true;
+                    }
+                    animation.frames.push(Frame {
+                        control: frame_control,
+                        data: Vec::new(),
+                    });
+                }
+                FrameData::CHUNK_TYPE => {
+                    chunk.expect_type(FrameData::CHUNK_TYPE)?;
+                    chunk.expect_min_len(4)?;
+                    animation.check_sequence(
+                        chunk::read_u32(chunk.data(), 0),
+                        &mut next_sequence_number,
+                    );
+                    match animation.frames.last_mut() {
+                        Some(frame) if seen_image_data => {
+                            frame.data.push(chunk.data()[4..].to_vec())
+                        }
+                        _ => animation
+                            .issues
+                            .push("fdAT chunk found before the default image or any fcTL".into()),
+                    }
+                }
+                "IDAT" => {
+                    seen_image_data = true;
+                    if animation.default_image_is_frame {
+                        if let Some(frame) = animation.frames.first_mut() {
+                            frame.data.push(chunk.data().to_vec());
+                        }
+                    }
+                }
+                "IEND" | AnimationControl::CHUNK_TYPE => {}
+                // Anything ahead of the image data (PLTE, tRNS, gAMA...) applies to every frame
+                _ if !seen_image_data => animation.shared_chunks.push(chunk.clone()),
+                _ => {}
+            }
+        }
+
+        if animation.frames.len() != control.num_frames() as usize {
+            animation.issues.push(format!(
+                "acTL declares {} frames but {} fcTL chunks were found",
+                control.num_frames(),
+                animation.frames.len()
+            ));
+        }
+        if let Some(first) = animation.frames.first() {
+            let control = first.control;
+            if animation.default_image_is_frame
+                && (control.x_offset != 0
+                    || control.y_offset != 0
+                    || control.width != header.width()
+                    || control.height != header.height())
+            {
+                animation
+                    .issues
+                    .push("The first frame must cover the whole default image".into());
+            }
+        }
+        for (idx, frame) in animation.frames.iter().enumerate() {
+            if frame.data.is_empty() {
+                animation
+                    .issues
+                    .push(format!("Frame {idx} has no image data"));
+            }
+        }
+
+        Ok(animation)
+    }
+
+    pub fn header(&self) -> &ImageHeader {
+        &self.header
+    }
+
+    pub fn control(&self) -> &AnimationControl {
+        &self.control
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn default_image_is_frame(&self) -> bool {
+        self.default_image_is_frame
+    }
+
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    // Frames are extracted as stored, without compositing them onto the previous frames
+    pub fn frame_png(&self, idx: usize) -> crate::Result<Png> {
+        let frame = self
+            .frames
+            .get(idx)
+            .ok_or(format!("Frame {idx} not present"))?;
+        let header = ImageHeader::new(
+            frame.control.width,
+            frame.control.height,
+            self.header.bit_depth(),
+            self.header.color_type(),
+            self.header.interlaced(),
+        )?;
+
+        let mut chunks = vec![header.to_chunk()];
+        chunks.extend(self.shared_chunks.iter().cloned());
+        for data in &frame.data {
+            chunks.push(Chunk::new(ChunkType::IDAT, data.clone()));
+        }
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+        Ok(Png::from_chunks(chunks))
+    }
+
+    // The first frame doubles as the default image and defines the canvas size
+    pub fn assemble(frames: &[(Png, FrameSettings)], num_plays: u32) -> crate::Result<Png> {
+        let (first, _) = frames.first().ok_or("At least one frame is required")?;
+        let canvas = ImageHeader::from_png(first)?;
+        let control = AnimationControl::new(frames.len().try_into()?, num_plays)?;
+        let palette = first.chunk_by_type("PLTE");
+
+        let mut chunks = vec![first.chunks()[0].clone(), control.to_chunk()];
+        chunks.extend(
+            first
+                .chunks()
+                .iter()
+                .filter(|chunk| {
+                    let chunk_type = chunk.chunk_type().to_string();
+                    !["IHDR", "IDAT", "IEND"].contains(&chunk_type.as_str())
+                        && !Self::CHUNK_TYPES.contains(&chunk_type.as_str())
+                })
+                .cloned(),
+        );
+
+        let mut sequence_number = 0;
+        for (idx, (png, settings)) in frames.iter().enumerate() {
+            if Self::is_animated(png) {
+                return Err(format!("Frame {idx} is already an animated PNG").into());
+            }
+            let header = ImageHeader::from_png(png)?;
+            if header.bit_depth() != canvas.bit_depth()
+                || header.color_type() != canvas.color_type()
+                || header.interlaced() != canvas.interlaced()
+            {
+                return Err(
+                    format!("Frame {idx} is {header} but the first frame is {canvas}").into(),
+                );
+            }
+            if png.chunk_by_type("PLTE").map(|c| c.data()) != palette.map(|c| c.data()) {
+                return Err(format!("Frame {idx} uses a different palette").into());
+            }
+
+            let frame_control = FrameControl {
+                sequence_number,
+                width: header.width(),
+                height: header.height(),
+                x_offset: settings.x_offset,
+                y_offset: settings.y_offset,
+                delay_num: settings.delay_num,
+                delay_den: settings.delay_den,
+                dispose_op: settings.dispose_op,
+                blend_op: settings.blend_op,
+            };
+            let right = settings.x_offset as u64 + header.width() as u64;
+            let bottom = settings.y_offset as u64 + header.height() as u64;
+            if right > canvas.width() as u64 || bottom > canvas.height() as u64 {
+                return Err(format!(
+                    "Frame {idx} does not fit on the {}x{} canvas",
+                    canvas.width(),
+                    canvas.height()
+                )
+                .into());
+            }
+            if idx == 0 && (settings.x_offset != 0 || settings.y_offset != 0) {
+                return Err("The first frame can't be offset".into());
+            }
+            chunks.push(frame_control.to_chunk());
+            sequence_number += 1;
+
+            let image_data = png
+                .chunks()
+                .iter()
+                .filter(|chunk| chunk.chunk_type().to_string() == "IDAT");
+            for chunk in image_data {
+                if idx == 0 {
+                    chunks.push(chunk.clone());
+                } else {
+                    chunks.push(FrameData::to_chunk(sequence_number, chunk.data()));
+                    sequence_number += 1;
+                }
+            }
+        }
+
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+        Ok(Png::from_chunks(chunks))
+    }
+
+    fn check_sequence(&mut self, sequence_number: u32, next_sequence_number: &mut u32) {
+        if sequence_number != *next_sequence_number {
+            self.issues.push(format!(
+                "Sequence number {sequence_number} found where {next_sequence_number} was expected"
+            ));
+        }
+        *next_sequence_number = sequence_number.wrapping_add(1);
+    }
+
+    fn check_region(&mut self, control: &FrameControl) {
+        let right = control.x_offset as u64 + control.width as u64;
+        let bottom = control.y_offset as u64 + control.height as u64;
+        if right > self.header.width() as u64 || bottom > self.header.height() as u64 {
+            self.issues.push(format!(
+                "Frame region of fcTL {} lies outside the {}x{} canvas",
+                control.sequence_number,
+                self.header.width(),
+                self.header.height()
+            ));
+        }
+    }
+}
+
+impl Display for Animation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plays = match self.control.num_plays() {
+            0 => "loops forever".to_string(),
+            n => format!("plays {n} time(s)"),
+        };
+        writeln!(f, "Animation: {} frames, {plays}", self.frames.len())?;
+        if !self.default_image_is_frame {
+            writeln!(f, "  Default image is not part of the animation")?;
+        }
+        for (idx, frame) in self.frames.iter().enumerate() {
+            let control = frame.control;
+            writeln!(
+                f,
+                "  Frame {idx}: seq={}, {}x{} at ({}, {}), delay {:.3}s, dispose {:?}, blend {:?}",
+                control.sequence_number,
+                control.width,
+                control.height,
+                control.x_offset,
+                control.y_offset,
+                control.delay(),
+                control.dispose_op,
+                control.blend_op
+            )?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "  Issue: {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ihdr::ColorType;
+    use std::str::FromStr;
+
+    fn frame_control(sequence_number: u32, width: u32, height: u32) -> FrameControl {
+        FrameControl {
+            sequence_number,
+            width,
+            height,
+            x_offset: 0,
+            y_offset: 0,
+            delay_num: 1,
+            delay_den: 10,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        }
+    }
+
+    fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn testing_apng(second_sequence_number: u32) -> Png {
+        let header = ImageHeader::new(4, 4, 8, ColorType::Rgba, false).unwrap();
+        Png::from_chunks(vec![
+            header.to_chunk(),
+            AnimationControl::new(2, 0).unwrap().to_chunk(),
+            chunk("gAMA", vec![0, 0, 177, 143]),
+            frame_control(0, 4, 4).to_chunk(),
+            chunk("IDAT", vec![1, 2, 3]),
+            frame_control(second_sequence_number, 2, 2).to_chunk(),
+            FrameData::to_chunk(second_sequence_number + 1, &[4, 5, 6]),
+            chunk("IEND", vec![]),
+        ])
+    }
+
+    fn testing_frame(width: u32, height: u32, data: Vec<u8>) -> Png {
+        let header = ImageHeader::new(width, height, 8, ColorType::Rgba, false).unwrap();
+        Png::from_chunks(vec![
+            header.to_chunk(),
+            chunk("gAMA", vec![0, 0, 177, 143]),
+            chunk("IDAT", data),
+            chunk("IEND", vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_assemble_round_trip() {
+        let settings = FrameSettings::from_delay_ms(100);
+        let frames = vec![
+            (testing_frame(4, 4, vec![1, 2, 3]), settings),
+            (testing_frame(2, 2, vec![4, 5, 6]), settings),
+            (testing_frame(4, 4, vec![7, 8, 9]), settings),
+        ];
+        let png = Animation::assemble(&frames, 0).unwrap();
+        let animation = Animation::from_png(&png).unwrap();
+
+        assert!(animation.issues().is_empty(), "{:?}", animation.issues());
+        assert!(animation.default_image_is_frame());
+        assert_eq!(animation.frames().len(), 3);
+        let sequence_numbers: Vec<u32> = animation
+            .frames()
+            .iter()
+            .map(|frame| frame.control().sequence_number)
+            .collect();
+        assert_eq!(sequence_numbers, [0, 1, 3]);
+        assert_eq!(animation.frames()[2].data(), [vec![7, 8, 9]]);
+        assert_eq!(animation.frames()[1].control().delay(), 0.1);
+    }
+
+    #[test]
+    fn test_assemble_rejects_mismatched_frames() {
+        let settings = FrameSettings::from_delay_ms(100);
+        let frames = vec![
+            (testing_frame(2, 2, vec![1]), settings),
+            (testing_frame(4, 4, vec![2]), settings),
+        ];
+        assert!(Animation::assemble(&frames, 0).is_err());
+
+        let gray = ImageHeader::new(2, 2, 8, ColorType::Grayscale, false).unwrap();
+        let gray = Png::from_chunks(vec![gray.to_chunk(), chunk("IEND", vec![])]);
+        let frames = vec![(testing_frame(2, 2, vec![1]), settings), (gray, settings)];
+        assert!(Animation::assemble(&frames, 0).is_err());
+        assert!(Animation::assemble(&[], 0).is_err());
+    }
+
+    #[test]
+    fn test_frame_control_round_trip() {
+        let control = frame_control(7, 10, 20);
+        let decoded = FrameControl::try_from(&control.to_chunk()).unwrap();
+        assert_eq!(decoded, control);
+        assert_eq!(decoded.delay(), 0.1);
+    }
+
+    #[test]
+    fn test_zero_delay_denominator() {
+        let mut control = frame_control(0, 1, 1);
+        control.delay_den = 0;
+        control.delay_num = 5;
+        assert_eq!(control.delay(), 0.05);
+    }
+
+    #[test]
+    fn test_parse_animation() {
+        let animation = Animation::from_png(&testing_apng(1)).unwrap();
+        assert!(animation.issues().is_empty(), "{:?}", animation.issues());
+        assert!(animation.default_image_is_frame());
+        assert_eq!(animation.frames().len(), 2);
+        assert_eq!(animation.frames()[1].data(), [vec![4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_sequence_number_gap() {
+        let animation = Animation::from_png(&testing_apng(2)).unwrap();
+        assert_eq!(
+            animation.issues(),
+            ["Sequence number 2 found where 1 was expected"]
+        );
+    }
+
+    #[test]
+    fn test_not_animated() {
+        let header = ImageHeader::new(4, 4, 8, ColorType::Rgba, false).unwrap();
+        let png = Png::from_chunks(vec![header.to_chunk(), chunk("IEND", vec![])]);
+        assert!(!Animation::is_animated(&png));
+        assert!(Animation::from_png(&png).is_err());
+    }
+
+    #[test]
+    fn test_frame_png() {
+        let animation = Animation::from_png(&testing_apng(1)).unwrap();
+        let frame = animation.frame_png(1).unwrap();
+        let types: Vec<String> = frame
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "IDAT", "IEND"]);
+        let header = ImageHeader::from_png(&frame).unwrap();
+        assert_eq!((header.width(), header.height()), (2, 2));
+        assert_eq!(frame.chunk_by_type("IDAT").unwrap().data(), &[4, 5, 6]);
+        assert!(animation.frame_png(2).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use std::path::Path;
+
+// Layout: MAGIC | version | name len (u16) | name | mime len (u8) | mime | size (u64) | sha256 | data
+pub const MAGIC: [u8; 4] = *b"PNGF";
+const VERSION: u8 = 1;
+const HASH_LEN: usize = 32;
+
+const MIME_TYPES: [(&str, &str); 16] = [
+    ("txt", "text/plain"),
+    ("json", "application/json"),
+    ("toml", "application/toml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("xml", "application/xml"),
+    ("html", "text/html"),
+    ("csv", "text/csv"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+];
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attachment {
+    filename: String,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(filename: &str, mime_type: &str, data: Vec<u8>) -> crate::Result<Self> {
+        let filename = sanitize_filename(filename)?;
+        if filename.len() > u16::MAX as usize {
+            return Err("Attachment filename is too long".into());
+        }
+        if mime_type.len() > u8::MAX as usize || !mime_type.is_ascii() {
+            return Err(format!("Invalid MIME type: {mime_type}").into());
+        }
+        Ok(Self {
+            filename,
+            mime_type: mime_type.to_string(),
+            data,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> crate::Result<Self> {
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(format!("Invalid file name: {}", path.display()))?;
+        Self::new(filename, guess_mime_type(filename), std::fs::read(path)?)
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn hash(&self) -> [u8; HASH_LEN] {
+        Sha256::digest(&self.data).into()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend((self.filename.len() as u16).to_be_bytes());
+        bytes.extend(self.filename.as_bytes());
+        bytes.push(self.mime_type.len() as u8);
+        bytes.extend(self.mime_type.as_bytes());
+        bytes.extend(self.size().to_be_bytes());
+        bytes.extend(self.hash());
+        bytes.extend(&self.data);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for Attachment {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if !is_attachment(bytes) {
+            return Err("Payload is not an attachment".into());
+        }
+        let mut reader = ByteReader {
+            bytes: &bytes[MAGIC.len()..],
+        };
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("Unsupported attachment version: {version}").into());
+        }
+        let name_len = u16::from_be_bytes(reader.take(2)?.try_into()?) as usize;
+        let filename = String::from_utf8(reader.take(name_len)?.to_vec())?;
+        let mime_len = reader.take(1)?[0] as usize;
+        let mime_type = String::from_utf8(reader.take(mime_len)?.to_vec())?;
+        let size = u64::from_be_bytes(reader.take(8)?.try_into()?);
+        let hash: [u8; HASH_LEN] = reader.take(HASH_LEN)?.try_into()?;
+        if reader.bytes.len() as u64 != size {
+            return Err(format!(
+                "Attachment size mismatch. Expected {size}, found {}",
+                reader.bytes.len()
+            )
+            .into());
+        }
+
+        let attachment = Self::new(&filename, &mime_type, reader.bytes.to_vec())?;
+        if attachment.hash() != hash {
+            return Err("Attachment hash mismatch, the data is corrupted".into());
+        }
+        Ok(attachment)
+    }
+}
+
+impl Display for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes, {}, sha256 {})",
+            self.filename,
+            self.size(),
+            self.mime_type,
+            hex::encode(self.hash())
+        )
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err("Attachment header is truncated".into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+}
+
+pub fn is_attachment(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+pub fn guess_mime_type(filename: &str) -> &'static str {
+    let extension = match filename.rsplit_once('.') {
+        Some((_, extension)) => extension.to_ascii_lowercase(),
+        None => return DEFAULT_MIME_TYPE,
+    };
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map_or(DEFAULT_MIME_TYPE, |(_, mime_type)| mime_type)
+}
+
+// Only the last path component is kept so extracting can't write outside the target directory
+fn sanitize_filename(filename: &str) -> crate::Result<String> {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+        return Err(format!("Invalid attachment filename: {filename:?}").into());
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_attachment() -> Attachment {
+        Attachment::new(
+            "config.json",
+            "application/json",
+            b"{\"debug\": true}".to_vec(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_attachment_round_trip() {
+        let attachment = testing_attachment();
+        let bytes = attachment.as_bytes();
+        assert!(is_attachment(&bytes));
+        assert_eq!(Attachment::try_from(bytes.as_slice()).unwrap(), attachment);
+    }
+
+    #[test]
+    fn test_corrupted_attachment() {
+        let mut bytes = testing_attachment().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(Attachment::try_from(bytes.as_slice()).is_err());
+        assert!(Attachment::try_from(&bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn test_filename_is_sanitized() {
+        let attachment = Attachment::new("../../etc/passwd", DEFAULT_MIME_TYPE, vec![]).unwrap();
+        assert_eq!(attachment.filename(), "passwd");
+        assert!(Attachment::new("..", DEFAULT_MIME_TYPE, vec![]).is_err());
+        assert!(Attachment::new("dir/", DEFAULT_MIME_TYPE, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_guess_mime_type() {
+        assert_eq!(guess_mime_type("bundle.TOML"), "application/toml");
+        assert_eq!(guess_mime_type("image.png"), "image/png");
+        assert_eq!(guess_mime_type("README"), DEFAULT_MIME_TYPE);
+    }
+}
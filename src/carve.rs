@@ -0,0 +1,216 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::{self, ChunkType};
+use crate::png::Png;
+use std::fmt::Display;
+
+const OVERHEAD: usize =
+    Chunk::DATA_LEN_BYTES_LEN + Chunk::CHUNK_TYPE_BYTES_LEN + Chunk::CRC_BYTES_LEN;
+
+#[derive(Debug)]
+pub struct Carved {
+    offset: usize,
+    length: usize,
+    complete: bool,
+    png: Png,
+    issues: Vec<String>,
+}
+
+impl Carved {
+    // Where the PNG signature starts in the blob
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // How many bytes of the blob the recovered chunks span
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    // Reached IEND without having to skip or patch anything
+    pub fn complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn png(&self) -> &Png {
+        &self.png
+    }
+
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+}
+
+impl Display for Carved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Offset {}: {} bytes, {} chunks, {}",
+            self.offset,
+            self.length,
+            self.png.chunks().len(),
+            if self.complete { "complete" } else { "partial" }
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "  Warning: {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+// Scans for the PNG signature anywhere in the blob and walks chunks from each hit.
+// Scanning resumes after the recovered chunks, so PNGs stored inside chunks aren't reported twice.
+pub fn carve(blob: &[u8]) -> Vec<Carved> {
+    let mut carved = Vec::new();
+    let mut start = 0;
+    while let Some(found) = find_header(blob, start) {
+        match carve_at(blob, found) {
+            Some(png) => {
+                start = found + png.length;
+                carved.push(png);
+            }
+            None => start = found + 1,
+        }
+    }
+    carved
+}
+
+fn find_header(blob: &[u8], from: usize) -> Option<usize> {
+    blob.get(from..)?
+        .windows(Png::STD_HEADER_LENGTH)
+        .position(|window| window == Png::STANDARD_HEADER)
+        .map(|idx| from + idx)
+}
+
+// None when not a single chunk follows the signature, it was most likely a chance match
+fn carve_at(blob: &[u8], offset: usize) -> Option<Carved> {
+    let mut chunks = Vec::new();
+    let mut issues = Vec::new();
+    let mut pos = offset + Png::STD_HEADER_LENGTH;
+    let mut reached_end = false;
+
+    while let Some(length_bytes) = blob.get(pos..).and_then(|rest| rest.first_chunk::<4>()) {
+        let length = u32::from_be_bytes(*length_bytes) as usize;
+        let Some(chunk_bytes) = length
+            .checked_add(OVERHEAD)
+            .filter(|_| length <= Chunk::MAX_LENGTH)
+            .and_then(|chunk_len| blob.get(pos..pos.checked_add(chunk_len)?))
+        else {
+            issues.push(format!(
+                "Chunk {} at offset {pos} is truncated or has a corrupt length",
+                chunks.len()
+            ));
+            break;
+        };
+        let chunk = match Chunk::try_from(chunk_bytes) {
+            Ok(chunk) => chunk,
+            Err(_) => {
+                // A damaged CRC is recoverable as long as the type still looks right
+                let type_bytes = [
+                    chunk_bytes[4],
+                    chunk_bytes[5],
+                    chunk_bytes[6],
+                    chunk_bytes[7],
+                ];
+                match ChunkType::checked(type_bytes).filter(ChunkType::is_valid) {
+                    Some(chunk_type) => {
+                        issues.push(format!(
+                            "Chunk {} ({chunk_type}) at offset {pos} has a bad CRC, kept its data",
+                            chunks.len()
+                        ));
+                        Chunk::new(chunk_type, chunk_bytes[8..8 + length].to_vec())
+                    }
+                    None => {
+                        issues.push(format!(
+                            "Chunk {} at offset {pos} has an invalid type {}",
+                            chunks.len(),
+                            chunk_type::escape(&type_bytes)
+                        ));
+                        break;
+                    }
+                }
+            }
+        };
+        pos += chunk_bytes.len();
+        reached_end = chunk.chunk_type() == &ChunkType::IEND;
+        chunks.push(chunk);
+        if reached_end {
+            break;
+        }
+    }
+
+    if chunks.is_empty() {
+        return None;
+    }
+    if !reached_end {
+        issues.push("No IEND chunk, appended one".to_string());
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+    }
+    Some(Carved {
+        offset,
+        length: pos - offset,
+        complete: issues.is_empty(),
+        png: Png::from_chunks(chunks),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_carve_embedded_pngs() {
+        let png = testing_png();
+        let mut blob = b"disk image header".to_vec();
+        blob.extend(&png);
+        blob.extend([0; 100]);
+        blob.extend(&png);
+        let carved = carve(&blob);
+        assert_eq!(carved.len(), 2);
+        assert_eq!(carved[0].offset(), 17);
+        assert_eq!(carved[1].offset(), 17 + png.len() + 100);
+        assert!(carved.iter().all(Carved::complete));
+        assert_eq!(carved[0].length(), png.len());
+        assert_eq!(carved[0].png().as_bytes(), png);
+    }
+
+    #[test]
+    fn test_carve_partial_png() {
+        let png = testing_png();
+        let mut blob = vec![0xaa; 5];
+        // Cut in the middle of ruSt and flip a byte of the IHDR CRC
+        blob.extend(&png[..png.len() - 20]);
+        blob[5 + 8 + 8 + 13] ^= 0xff;
+        let carved = carve(&blob);
+        assert_eq!(carved.len(), 1);
+        let partial = &carved[0];
+        assert!(!partial.complete());
+        assert_eq!(partial.issues().len(), 3);
+        assert!(partial.issues()[0].contains("bad CRC"));
+        let types: Vec<String> = partial
+            .png()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IEND"]);
+    }
+
+    #[test]
+    fn test_chance_signature_is_ignored() {
+        let mut blob = Png::STANDARD_HEADER.to_vec();
+        blob.extend([0xff; 16]);
+        assert!(carve(&blob).is_empty());
+        assert!(carve(&[]).is_empty());
+    }
+}
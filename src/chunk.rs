@@ -34,12 +34,22 @@ impl TryFrom<&[u8]> for Chunk {
 
         let (data_len_bytes, value) = value.split_at(Chunk::DATA_LEN_BYTES_LEN);
         let (chunk_type_bytes, value) = value.split_at(Chunk::CHUNK_TYPE_BYTES_LEN);
+        let length = u32::from_be_bytes(data_len_bytes.try_into()?) as usize;
+        if length > Chunk::MAX_LENGTH {
+            return Err(format!("Chunk length {length} exceeds the 2^31-1 limit").into());
+        }
+        if value.len() != length + Chunk::CRC_BYTES_LEN {
+            return Err(format!(
+                "Chunk declares {length} data bytes, found {}",
+                value.len().saturating_sub(Chunk::CRC_BYTES_LEN)
+            )
+            .into());
+        }
         let chunk_type_bytes: [u8; 4] = chunk_type_bytes.try_into()?;
         let type_: ChunkType = chunk_type_bytes.try_into()?;
         if !type_.is_valid() {
             return Err(format!("Chunk type not valid. Chunk_Type: {type_}").into());
         }
-        let length = u32::from_be_bytes(data_len_bytes.try_into()?) as usize;
         let (data_bytes, crc_bytes) = value.split_at(length);
         let data: Vec<u8> = data_bytes.into();
         let expected_crc: u32 = u32::from_be_bytes(crc_bytes.try_into()?);
@@ -56,7 +66,7 @@ impl TryFrom<&[u8]> for Chunk {
             return Err(format!("CRC mismatch. Bytes might have been corrupted\nCRC (Expected : Actual) {expected_crc} : {crc_}").into());
         }
 
-        return Ok(payload);
+        Ok(payload)
     }
 }
 
@@ -64,17 +74,12 @@ impl Chunk {
     pub const CHUNK_TYPE_BYTES_LEN: usize = 4;
     pub const DATA_LEN_BYTES_LEN: usize = 4;
     pub const CRC_BYTES_LEN: usize = 4;
+    // The spec caps chunk lengths at 2^31-1, decoders often give up far sooner
+    pub const MAX_LENGTH: usize = i32::MAX as usize;
 
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
-        let bytes = chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect::<Vec<u8>>();
-
         let mut payload = Chunk {
-            length: bytes.len() as u32,
+            length: data.len() as u32,
             data,
             type_: chunk_type,
             crc: 0,
@@ -107,6 +112,38 @@ impl Chunk {
         &self.data
     }
 
+    // Typed chunks check what they were handed before reading any fields
+    pub fn expect_type(&self, chunk_type: &str) -> crate::Result<()> {
+        if self.type_.to_string() != chunk_type {
+            return Err(format!("Expected a {chunk_type} chunk, found {}", self.type_).into());
+        }
+        Ok(())
+    }
+
+    pub fn expect_len(&self, data_len: usize) -> crate::Result<()> {
+        if self.data.len() != data_len {
+            return Err(format!(
+                "Invalid {} data length. Expected {data_len}, found {}",
+                self.type_,
+                self.data.len()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn expect_min_len(&self, min_len: usize) -> crate::Result<()> {
+        if self.data.len() < min_len {
+            return Err(format!(
+                "{} data too short. Expected at least {min_len} bytes, found {}",
+                self.type_,
+                self.data.len()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     pub fn data_as_string(&self) -> crate::Result<String> {
         Ok(String::from_utf8(self.data.clone())?)
     }
@@ -123,6 +160,15 @@ impl Chunk {
     }
 }
 
+// Callers check the length first, these index straight into the data
+pub fn read_u16(data: &[u8], idx: usize) -> u16 {
+    u16::from_be_bytes([data[idx], data[idx + 1]])
+}
+
+pub fn read_u32(data: &[u8], idx: usize) -> u32 {
+    u32::from_be_bytes([data[idx], data[idx + 1], data[idx + 2], data[idx + 3]])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,6 +293,6 @@ mod tests {
             .copied()
             .collect();
 
-        let chunk: Chunk = TryFrom::try_from(chunk_data.as_ref()).unwrap();
+        let _chunk: Chunk = TryFrom::try_from(chunk_data.as_ref()).unwrap();
     }
 }
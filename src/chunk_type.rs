@@ -1,3 +1,6 @@
+use crate::registry::{self, ChunkInfo};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
 use std::convert::TryFrom;
 use std::fmt::Display;
 use std::str::FromStr;
@@ -9,7 +12,11 @@ pub struct ChunkType {
 
 impl Display for ChunkType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", String::from_utf8(self.bytes.into()).unwrap())
+        // Construction guarantees ASCII letters, so every byte is a char
+        for b in self.bytes {
+            write!(f, "{}", b as char)?;
+        }
+        Ok(())
     }
 }
 
@@ -17,7 +24,11 @@ impl FromStr for ChunkType {
     type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::try_from(s.to_owned())
+        let bytes: [u8; 4] = s
+            .as_bytes()
+            .try_into()
+            .map_err(|_| format!("Invalid chunk type length {}, expected 4", s.len()))?;
+        Self::try_from(bytes)
     }
 }
 
@@ -25,20 +36,7 @@ impl TryFrom<String> for ChunkType {
     type Error = crate::Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if value.len() > 4 || value.len() < 4 {
-            return Err("Invalid string length".into());
-        }
-
-        let mut bytes = [0u8; 4];
-
-        for (idx, b) in value.into_bytes().into_iter().enumerate() {
-            if !b.is_ascii_alphabetic() {
-                return Err("All characters must be alphabetic".into());
-            }
-            bytes[idx] = b;
-        }
-
-        return Ok(Self { bytes });
+        Self::from_str(&value)
     }
 }
 
@@ -46,62 +44,138 @@ impl TryFrom<[u8; 4]> for ChunkType {
     type Error = crate::Error;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        if !value.is_ascii() {
-            return Err("Invalid byte: Byte must be in range [65-90] or [97-122]".into());
-        }
-
-        return Ok(Self { bytes: value });
+        Self::checked(value).ok_or_else(|| {
+            format!(
+                "Invalid chunk type {}, all bytes must be ASCII letters",
+                escape(&value)
+            )
+            .into()
+        })
     }
 }
 
+// Shows any four bytes without losing information, for types that failed validation
+pub fn escape(bytes: &[u8; 4]) -> String {
+    bytes.escape_ascii().to_string()
+}
+
 impl ChunkType {
+    pub const IHDR: Self = Self::from_letters(*b"IHDR");
+    pub const PLTE: Self = Self::from_letters(*b"PLTE");
+    pub const IDAT: Self = Self::from_letters(*b"IDAT");
+    pub const IEND: Self = Self::from_letters(*b"IEND");
+    pub const TEXT: Self = Self::from_letters(*b"tEXt");
+    pub const ZTXT: Self = Self::from_letters(*b"zTXt");
+    pub const ITXT: Self = Self::from_letters(*b"iTXt");
+    pub const PHYS: Self = Self::from_letters(*b"pHYs");
+    pub const TIME: Self = Self::from_letters(*b"tIME");
+    pub const GAMA: Self = Self::from_letters(*b"gAMA");
+    pub const CHRM: Self = Self::from_letters(*b"cHRM");
+    pub const SRGB: Self = Self::from_letters(*b"sRGB");
+    pub const ICCP: Self = Self::from_letters(*b"iCCP");
+    pub const CICP: Self = Self::from_letters(*b"cICP");
+    pub const TRNS: Self = Self::from_letters(*b"tRNS");
+    pub const BKGD: Self = Self::from_letters(*b"bKGD");
+    pub const HIST: Self = Self::from_letters(*b"hIST");
+    pub const SBIT: Self = Self::from_letters(*b"sBIT");
+    pub const SPLT: Self = Self::from_letters(*b"sPLT");
+    pub const EXIF: Self = Self::from_letters(*b"eXIf");
+    pub const ACTL: Self = Self::from_letters(*b"acTL");
+    pub const FCTL: Self = Self::from_letters(*b"fcTL");
+    pub const FDAT: Self = Self::from_letters(*b"fdAT");
+    pub const SIGN: Self = Self::from_letters(*b"siGN");
+
+    // The one validation path, every other constructor goes through here
+    pub const fn checked(bytes: [u8; 4]) -> Option<Self> {
+        let mut idx = 0;
+        while idx < bytes.len() {
+            if !bytes[idx].is_ascii_alphabetic() {
+                return None;
+            }
+            idx += 1;
+        }
+        Some(Self { bytes })
+    }
+
+    // For constants, an invalid type fails the build instead of panicking at runtime
+    pub const fn from_letters(bytes: [u8; 4]) -> Self {
+        match Self::checked(bytes) {
+            Some(chunk_type) => chunk_type,
+            None => panic!("chunk types must be four ASCII letters"),
+        }
+    }
+
     pub fn bytes(&self) -> [u8; 4] {
         self.bytes.to_owned()
     }
 
+    pub fn known_info(&self) -> Option<&'static ChunkInfo> {
+        registry::lookup(&self.bytes)
+    }
+
     pub fn is_valid(&self) -> bool {
-        for b in self.bytes {
-            if !b.is_ascii() {
-                return false;
-            }
-        }
-        return self.is_reserved_bit_valid();
+        self.is_reserved_bit_valid()
     }
 
     pub fn is_critical(&self) -> bool {
         let fifth_bit = (self.bytes[0] >> 5u8) & 1u8;
 
-        if fifth_bit == 0 {
-            return true;
-        }
-        return false;
+        fifth_bit == 0
     }
 
     pub fn is_public(&self) -> bool {
         let fifth_bit = (self.bytes[1] >> 5u8) & 1u8;
 
-        if fifth_bit == 0 {
-            return true;
-        }
-        return false;
+        fifth_bit == 0
     }
 
     pub fn is_reserved_bit_valid(&self) -> bool {
         let fifth_bit = (self.bytes[2] >> 5u8) & 1u8;
 
-        if fifth_bit == 0 {
-            return true;
-        }
-        return false;
+        fifth_bit == 0
     }
 
     pub fn is_safe_to_copy(&self) -> bool {
         let fifth_bit = (self.bytes[3] >> 5u8) & 1u8;
 
-        if fifth_bit == 1 {
-            return true;
+        fifth_bit == 1
+    }
+
+    // Decoders skip these and editors keep them, which is what a hidden payload wants
+    pub fn is_private_ancillary(&self) -> bool {
+        !self.is_critical()
+            && !self.is_public()
+            && self.is_reserved_bit_valid()
+            && self.is_safe_to_copy()
+    }
+
+    // The same seed always gives the same type, registered types are skipped
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut counter = 0u32;
+        loop {
+            let digest = Sha256::new()
+                .chain_update(b"pngme chunk type")
+                .chain_update(counter.to_be_bytes())
+                .chain_update(seed)
+                .finalize();
+            let letter = |idx: usize| b'a' + digest[idx] % 26;
+            let bytes = [
+                letter(0),
+                letter(1),
+                letter(2).to_ascii_uppercase(),
+                letter(3),
+            ];
+            if registry::lookup(&bytes).is_none() {
+                return Self { bytes };
+            }
+            counter += 1;
         }
-        return false;
+    }
+
+    pub fn random_private() -> Self {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        Self::from_seed(&seed)
     }
 }
 
@@ -189,6 +263,39 @@ mod tests {
         assert!(!chunk.is_reserved_bit_valid());
     }
 
+    #[test]
+    pub fn test_generated_chunk_types_are_private_ancillary() {
+        let seeded = ChunkType::from_seed(b"correct horse");
+        assert_eq!(seeded, ChunkType::from_seed(b"correct horse"));
+        assert!(seeded.is_private_ancillary());
+        assert!(seeded.known_info().is_none());
+        for _ in 0..100 {
+            assert!(ChunkType::random_private().is_private_ancillary());
+        }
+        assert!(!ChunkType::from_str("RuSt").unwrap().is_private_ancillary());
+        assert!(!ChunkType::from_str("tEXt").unwrap().is_private_ancillary());
+    }
+
+    #[test]
+    pub fn test_byte_and_string_validation_agree() {
+        for bytes in [*b"Ru1t", *b"Ru t", *b"Ru\xffT", [0; 4]] {
+            assert!(ChunkType::try_from(bytes).is_err());
+            assert!(ChunkType::checked(bytes).is_none());
+        }
+        assert!(ChunkType::from_str("Ru1t").is_err());
+        assert!(ChunkType::from_str("RuStt").is_err());
+        assert!(ChunkType::from_str("Ru\u{e9}").is_err());
+    }
+
+    #[test]
+    pub fn test_constants_and_escape() {
+        assert_eq!(ChunkType::IEND, ChunkType::from_str("IEND").unwrap());
+        assert_eq!(ChunkType::TEXT.to_string(), "tEXt");
+        assert_eq!(escape(b"a\x00\xffZ"), "a\\x00\\xffZ");
+        let err = ChunkType::try_from(*b"ab\n1").unwrap_err();
+        assert!(err.to_string().contains("ab\\n1"));
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -0,0 +1,255 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::ihdr::ImageHeader;
+use crate::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{LazyLock, PoisonError, RwLock};
+
+// Implemented by library users for their own chunk types
+pub trait ChunkCodec: Send + Sync {
+    type Value;
+
+    fn chunk_type(&self) -> ChunkType;
+
+    fn decode(&self, data: &[u8]) -> crate::Result<Self::Value>;
+
+    fn encode(&self, value: &Self::Value) -> crate::Result<Vec<u8>>;
+
+    fn render(&self, value: &Self::Value) -> String;
+
+    fn to_json(&self, value: &Self::Value) -> Value {
+        Value::String(self.render(value))
+    }
+
+    fn to_chunk(&self, value: &Self::Value) -> crate::Result<Chunk> {
+        Ok(Chunk::new(self.chunk_type(), self.encode(value)?))
+    }
+}
+
+// Object safe view of a codec so codecs with different value types share a registry
+trait ErasedCodec: Send + Sync {
+    // None when the codec has no text form of its own
+    fn render(&self, data: &[u8]) -> Option<crate::Result<String>>;
+
+    fn to_json(&self, data: &[u8], header: Option<&ImageHeader>) -> crate::Result<Value>;
+}
+
+impl<C: ChunkCodec> ErasedCodec for C {
+    fn render(&self, data: &[u8]) -> Option<crate::Result<String>> {
+        Some(
+            self.decode(data)
+                .map(|value| ChunkCodec::render(self, &value)),
+        )
+    }
+
+    fn to_json(&self, data: &[u8], _header: Option<&ImageHeader>) -> crate::Result<Value> {
+        Ok(ChunkCodec::to_json(self, &self.decode(data)?))
+    }
+}
+
+pub type BuiltinJson = fn(&Chunk, Option<&ImageHeader>) -> crate::Result<Value>;
+
+// Standard chunks only bring their JSON, print keeps using the pretty printers of the registry
+struct Builtin {
+    chunk_type: ChunkType,
+    to_json: BuiltinJson,
+}
+
+impl ErasedCodec for Builtin {
+    fn render(&self, _data: &[u8]) -> Option<crate::Result<String>> {
+        None
+    }
+
+    fn to_json(&self, data: &[u8], header: Option<&ImageHeader>) -> crate::Result<Value> {
+        (self.to_json)(&Chunk::new(self.chunk_type.clone(), data.to_vec()), header)
+    }
+}
+
+#[derive(Default)]
+pub struct CodecRegistry {
+    codecs: HashMap<[u8; 4], Box<dyn ErasedCodec>>,
+}
+
+impl CodecRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for (chunk_type, to_json) in json::BUILTIN_FIELDS {
+            registry.codecs.insert(
+                chunk_type.bytes(),
+                Box::new(Builtin {
+                    chunk_type,
+                    to_json,
+                }),
+            );
+        }
+        registry
+    }
+
+    // A later codec for the same chunk type replaces the earlier one, built-in ones included
+    pub fn register<C: ChunkCodec + 'static>(&mut self, codec: C) {
+        self.codecs
+            .insert(codec.chunk_type().bytes(), Box::new(codec));
+    }
+
+    pub fn contains(&self, chunk_type: &ChunkType) -> bool {
+        self.codecs.contains_key(&chunk_type.bytes())
+    }
+
+    pub fn render(&self, chunk_type: &ChunkType, data: &[u8]) -> Option<crate::Result<String>> {
+        self.codecs
+            .get(&chunk_type.bytes())
+            .and_then(|codec| codec.render(data))
+    }
+
+    pub fn to_json(&self, chunk_type: &ChunkType, data: &[u8]) -> Option<crate::Result<Value>> {
+        self.codecs
+            .get(&chunk_type.bytes())
+            .map(|codec| codec.to_json(data, None))
+    }
+
+    pub fn render_chunk(&self, chunk: &Chunk) -> Option<crate::Result<String>> {
+        self.render(chunk.chunk_type(), chunk.data())
+    }
+
+    // Some standard chunks, like tRNS, can only be decoded against the image header
+    pub fn chunk_to_json(
+        &self,
+        chunk: &Chunk,
+        header: Option<&ImageHeader>,
+    ) -> Option<crate::Result<Value>> {
+        self.codecs
+            .get(&chunk.chunk_type().bytes())
+            .map(|codec| codec.to_json(chunk.data(), header))
+    }
+}
+
+static GLOBAL: LazyLock<RwLock<CodecRegistry>> =
+    LazyLock::new(|| RwLock::new(CodecRegistry::with_builtins()));
+
+// Codecs registered here are picked up by print, decode and the JSON output
+pub fn register<C: ChunkCodec + 'static>(codec: C) {
+    // A panic elsewhere can't leave the map half updated, so a poisoned lock is still usable
+    GLOBAL
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register(codec);
+}
+
+pub fn with_registry<T>(f: impl FnOnce(&CodecRegistry) -> T) -> T {
+    f(&GLOBAL.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+pub fn render(chunk_type: &str, data: &[u8]) -> Option<crate::Result<String>> {
+    let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+    with_registry(|registry| registry.render(&chunk_type, data))
+}
+
+pub fn to_json(chunk_type: &str, data: &[u8]) -> Option<crate::Result<Value>> {
+    let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+    with_registry(|registry| registry.to_json(&chunk_type, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq)]
+    struct Version {
+        major: u16,
+        minor: u16,
+    }
+
+    struct VersionCodec;
+
+    impl ChunkCodec for VersionCodec {
+        type Value = Version;
+
+        fn chunk_type(&self) -> ChunkType {
+            ChunkType::from_str("veRs").unwrap()
+        }
+
+        fn decode(&self, data: &[u8]) -> crate::Result<Version> {
+            match data {
+                [a, b, c, d] => Ok(Version {
+                    major: u16::from_be_bytes([*a, *b]),
+                    minor: u16::from_be_bytes([*c, *d]),
+                }),
+                _ => Err(format!("Invalid veRs length {}", data.len()).into()),
+            }
+        }
+
+        fn encode(&self, value: &Version) -> crate::Result<Vec<u8>> {
+            let mut data = value.major.to_be_bytes().to_vec();
+            data.extend(value.minor.to_be_bytes());
+            Ok(data)
+        }
+
+        fn render(&self, value: &Version) -> String {
+            format!("v{}.{}", value.major, value.minor)
+        }
+
+        fn to_json(&self, value: &Version) -> Value {
+            json!({ "major": value.major, "minor": value.minor })
+        }
+    }
+
+    #[test]
+    fn test_codec_round_trip() {
+        let version = Version { major: 1, minor: 2 };
+        let chunk = VersionCodec.to_chunk(&version).unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "veRs");
+        assert_eq!(VersionCodec.decode(chunk.data()).unwrap(), version);
+    }
+
+    #[test]
+    fn test_registry_renders_registered_types() {
+        let mut registry = CodecRegistry::new();
+        registry.register(VersionCodec);
+        let chunk = VersionCodec
+            .to_chunk(&Version { major: 3, minor: 4 })
+            .unwrap();
+        assert!(registry.contains(chunk.chunk_type()));
+        assert_eq!(registry.render_chunk(&chunk).unwrap().unwrap(), "v3.4");
+        assert!(registry.render(chunk.chunk_type(), &[1]).unwrap().is_err());
+        let other = ChunkType::from_str("ruSt").unwrap();
+        assert!(registry.render(&other, b"hi").is_none());
+    }
+
+    #[test]
+    fn test_builtin_codecs_decode_standard_chunks() {
+        let registry = CodecRegistry::with_builtins();
+        let gamma = Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec());
+        assert_eq!(
+            registry.chunk_to_json(&gamma, None).unwrap().unwrap()["value"],
+            45455
+        );
+        // Their text form stays with the pretty printers of the chunk registry
+        assert!(registry.render_chunk(&gamma).is_none());
+        let transparency = Chunk::new(ChunkType::TRNS, vec![0, 1]);
+        assert!(registry
+            .chunk_to_json(&transparency, None)
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_registered_codec_feeds_json() {
+        // A local registry, the global one is shared by every test running in parallel
+        let mut registry = CodecRegistry::with_builtins();
+        registry.register(VersionCodec);
+        let chunk = VersionCodec
+            .to_chunk(&Version { major: 5, minor: 6 })
+            .unwrap();
+        assert_eq!(
+            registry.chunk_to_json(&chunk, None).unwrap().unwrap(),
+            json!({ "major": 5, "minor": 6 })
+        );
+    }
+}
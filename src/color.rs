@@ -0,0 +1,584 @@
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+// gAMA and cHRM store their values scaled by 100000
+const SCALE: f64 = 100_000.0;
+const SRGB_GAMMA: u32 = 45455;
+const SRGB_CHROMATICITIES: [u32; 8] = [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000];
+const GAMMA_TOLERANCE: u32 = 500;
+const CHROMATICITY_TOLERANCE: u32 = 1000;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Gamma {
+    value: u32,
+}
+
+impl Gamma {
+    pub const CHUNK_TYPE: &'static str = "gAMA";
+
+    pub fn new(value: u32) -> crate::Result<Self> {
+        if value == 0 {
+            return Err("Gamma must be greater than zero".into());
+        }
+        Ok(Self { value })
+    }
+
+    pub fn from_gamma(gamma: f64) -> crate::Result<Self> {
+        if !gamma.is_finite() || gamma <= 0.0 || gamma * SCALE > u32::MAX as f64 {
+            return Err(format!("Gamma out of range: {gamma}").into());
+        }
+        Self::new((gamma * SCALE).round() as u32)
+    }
+
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn gamma(&self) -> f64 {
+        self.value as f64 / SCALE
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::GAMA, self.value.to_be_bytes().to_vec())
+    }
+}
+
+impl TryFrom<&Chunk> for Gamma {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(4)?;
+        Self::new(chunk::read_u32(chunk.data(), 0))
+    }
+}
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.5}", self.gamma())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Chromaticities {
+    values: [u32; 8],
+}
+
+impl Chromaticities {
+    pub const CHUNK_TYPE: &'static str = "cHRM";
+
+    // Order: white x/y, red x/y, green x/y, blue x/y
+    pub fn new(values: [u32; 8]) -> Self {
+        Self { values }
+    }
+
+    pub fn from_points(points: &[f64]) -> crate::Result<Self> {
+        if points.len() != 8 {
+            return Err(format!(
+                "cHRM needs 8 values (white, red, green, blue x/y pairs), found {}",
+                points.len()
+            )
+            .into());
+        }
+        let mut values = [0u32; 8];
+        for (idx, point) in points.iter().enumerate() {
+            if !point.is_finite() || *point < 0.0 || *point * SCALE > i32::MAX as f64 {
+                return Err(format!("Chromaticity out of range: {point}").into());
+            }
+            values[idx] = (point * SCALE).round() as u32;
+        }
+        Ok(Self { values })
+    }
+
+    pub fn values(&self) -> [u32; 8] {
+        self.values
+    }
+
+    pub fn white_point(&self) -> (f64, f64) {
+        self.point(0)
+    }
+
+    pub fn red(&self) -> (f64, f64) {
+        self.point(1)
+    }
+
+    pub fn green(&self) -> (f64, f64) {
+        self.point(2)
+    }
+
+    pub fn blue(&self) -> (f64, f64) {
+        self.point(3)
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.values
+            .iter()
+            .zip(SRGB_CHROMATICITIES.iter())
+            .all(|(a, b)| a.abs_diff(*b) <= CHROMATICITY_TOLERANCE)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(
+            ChunkType::CHRM,
+            self.values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+        )
+    }
+
+    fn point(&self, idx: usize) -> (f64, f64) {
+        (
+            self.values[idx * 2] as f64 / SCALE,
+            self.values[idx * 2 + 1] as f64 / SCALE,
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for Chromaticities {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(32)?;
+        let mut values = [0u32; 8];
+        for (idx, value) in values.iter_mut().enumerate() {
+            *value = chunk::read_u32(chunk.data(), idx * 4);
+        }
+        Ok(Self { values })
+    }
+}
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = ["white", "red", "green", "blue"];
+        let points: Vec<String> = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let (x, y) = self.point(idx);
+                format!("{name}=({x:.4}, {y:.4})")
+            })
+            .collect();
+        write!(f, "{}", points.join(" "))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl RenderingIntent {
+    pub const CHUNK_TYPE: &'static str = "sRGB";
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SRGB, vec![*self as u8])
+    }
+}
+
+impl TryFrom<&Chunk> for RenderingIntent {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(1)?;
+        match chunk.data()[0] {
+            0 => Ok(Self::Perceptual),
+            1 => Ok(Self::RelativeColorimetric),
+            2 => Ok(Self::Saturation),
+            3 => Ok(Self::AbsoluteColorimetric),
+            other => Err(format!("Invalid sRGB rendering intent: {other}").into()),
+        }
+    }
+}
+
+impl Display for RenderingIntent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Perceptual => "perceptual",
+            Self::RelativeColorimetric => "relative colorimetric",
+            Self::Saturation => "saturation",
+            Self::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Cicp {
+    colour_primaries: u8,
+    transfer_function: u8,
+    matrix_coefficients: u8,
+    full_range: bool,
+}
+
+impl Cicp {
+    pub const CHUNK_TYPE: &'static str = "cICP";
+
+    pub fn new(
+        colour_primaries: u8,
+        transfer_function: u8,
+        matrix_coefficients: u8,
+        full_range: bool,
+    ) -> crate::Result<Self> {
+        // PNG only carries RGB samples, so the matrix must be the identity
+        if matrix_coefficients != 0 {
+            return Err(format!(
+                "cICP matrix coefficients must be 0 for PNG, found {matrix_coefficients}"
+            )
+            .into());
+        }
+        Ok(Self {
+            colour_primaries,
+            transfer_function,
+            matrix_coefficients,
+            full_range,
+        })
+    }
+
+    pub fn from_values(values: &[u8]) -> crate::Result<Self> {
+        match values {
+            [primaries, transfer, matrix, range] => {
+                if *range > 1 {
+                    return Err(
+                        format!("cICP full range flag must be 0 or 1, found {range}").into(),
+                    );
+                }
+                Self::new(*primaries, *transfer, *matrix, *range == 1)
+            }
+            _ => Err(format!("cICP needs 4 values, found {}", values.len()).into()),
+        }
+    }
+
+    pub fn values(&self) -> [u8; 4] {
+        [
+            self.colour_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            self.full_range as u8,
+        ]
+    }
+
+    pub fn primaries_name(&self) -> &'static str {
+        match self.colour_primaries {
+            1 => "BT.709",
+            9 => "BT.2020",
+            12 => "Display P3",
+            _ => "other",
+        }
+    }
+
+    pub fn transfer_name(&self) -> &'static str {
+        match self.transfer_function {
+            1 => "BT.709",
+            8 => "linear",
+            13 => "sRGB",
+            16 => "PQ",
+            18 => "HLG",
+            _ => "other",
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(
+            ChunkType::CICP,
+            vec![
+                self.colour_primaries,
+                self.transfer_function,
+                self.matrix_coefficients,
+                self.full_range as u8,
+            ],
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for Cicp {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(4)?;
+        Self::from_values(chunk.data())
+    }
+}
+
+impl Display for Cicp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "primaries={} ({}), transfer={} ({}), matrix={}, full_range={}",
+            self.colour_primaries,
+            self.primaries_name(),
+            self.transfer_function,
+            self.transfer_name(),
+            self.matrix_coefficients,
+            self.full_range
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ColorSpace {
+    gamma: Option<Gamma>,
+    chromaticities: Option<Chromaticities>,
+    srgb: Option<RenderingIntent>,
+    cicp: Option<Cicp>,
+    has_icc_profile: bool,
+    conflicts: Vec<String>,
+}
+
+impl ColorSpace {
+    pub const CHUNK_TYPES: [&'static str; 4] = [
+        Gamma::CHUNK_TYPE,
+        Chromaticities::CHUNK_TYPE,
+        RenderingIntent::CHUNK_TYPE,
+        Cicp::CHUNK_TYPE,
+    ];
+
+    pub fn from_png(png: &Png) -> Self {
+        let mut color_space = Self::default();
+        let mut seen_image_data = false;
+
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type().to_string();
+            if chunk_type == "PLTE" || chunk_type == "IDAT" {
+                seen_image_data = true;
+                continue;
+            }
+            if chunk_type == "iCCP" {
+                color_space.has_icc_profile = true;
+                continue;
+            }
+            if !Self::CHUNK_TYPES.contains(&chunk_type.as_str()) {
+                continue;
+            }
+            if seen_image_data {
+                color_space.conflicts.push(format!(
+                    "{chunk_type} appears after PLTE/IDAT and may be ignored"
+                ));
+            }
+            if let Err(e) = color_space.add_chunk(chunk) {
+                color_space.conflicts.push(e.to_string());
+            }
+        }
+
+        color_space.check_consistency();
+        color_space
+    }
+
+    pub fn gamma(&self) -> Option<&Gamma> {
+        self.gamma.as_ref()
+    }
+
+    pub fn chromaticities(&self) -> Option<&Chromaticities> {
+        self.chromaticities.as_ref()
+    }
+
+    pub fn srgb(&self) -> Option<&RenderingIntent> {
+        self.srgb.as_ref()
+    }
+
+    pub fn cicp(&self) -> Option<&Cicp> {
+        self.cicp.as_ref()
+    }
+
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    // Decoders honour cICP first, then iCCP, then sRGB, then gAMA/cHRM
+    pub fn effective(&self) -> String {
+        if let Some(cicp) = self.cicp {
+            format!(
+                "cICP ({} primaries, {} transfer)",
+                cicp.primaries_name(),
+                cicp.transfer_name()
+            )
+        } else if self.has_icc_profile {
+            "ICC profile (iCCP)".to_string()
+        } else if let Some(intent) = self.srgb {
+            format!("sRGB ({intent})")
+        } else if let Some(gamma) = self.gamma {
+            match self.chromaticities {
+                Some(_) => format!("gAMA/cHRM (gamma {gamma})"),
+                None => format!("gAMA (gamma {gamma})"),
+            }
+        } else if self.chromaticities.is_some() {
+            "cHRM only".to_string()
+        } else {
+            "untagged (viewers usually assume sRGB)".to_string()
+        }
+    }
+
+    fn add_chunk(&mut self, chunk: &Chunk) -> crate::Result<()> {
+        let chunk_type = chunk.chunk_type().to_string();
+        let duplicate = match chunk_type.as_str() {
+            Gamma::CHUNK_TYPE => self.gamma.replace(Gamma::try_from(chunk)?).is_some(),
+            Chromaticities::CHUNK_TYPE => self
+                .chromaticities
+                .replace(Chromaticities::try_from(chunk)?)
+                .is_some(),
+            RenderingIntent::CHUNK_TYPE => self
+                .srgb
+                .replace(RenderingIntent::try_from(chunk)?)
+                .is_some(),
+            Cicp::CHUNK_TYPE => self.cicp.replace(Cicp::try_from(chunk)?).is_some(),
+            _ => false,
+        };
+        if duplicate {
+            return Err(format!("Multiple {chunk_type} chunks present").into());
+        }
+        Ok(())
+    }
+
+    fn check_consistency(&mut self) {
+        if self.srgb.is_some() {
+            if let Some(gamma) = self.gamma {
+                if gamma.value().abs_diff(SRGB_GAMMA) > GAMMA_TOLERANCE {
+                    self.conflicts.push(format!(
+                        "sRGB present but gAMA is {gamma} (expected 0.45455)"
+                    ));
+                }
+            }
+            if let Some(chromaticities) = self.chromaticities {
+                if !chromaticities.is_srgb() {
+                    self.conflicts
+                        .push("sRGB present but cHRM does not match the sRGB primaries".into());
+                }
+            }
+            if self.has_icc_profile {
+                self.conflicts
+                    .push("sRGB and iCCP must not both be present".into());
+            }
+        }
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Color management: {}", self.effective())?;
+        if let Some(cicp) = self.cicp {
+            writeln!(f, "  cICP: {cicp}")?;
+        }
+        if let Some(intent) = self.srgb {
+            writeln!(f, "  sRGB: {intent}")?;
+        }
+        if let Some(gamma) = self.gamma {
+            writeln!(f, "  gAMA: {gamma}")?;
+        }
+        if let Some(chromaticities) = self.chromaticities {
+            writeln!(f, "  cHRM: {chromaticities}")?;
+        }
+        for conflict in &self.conflicts {
+            writeln!(f, "  Conflict: {conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png(chunks: Vec<Chunk>) -> Png {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![1, 2, 3]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        for chunk in chunks {
+            png.insert_before_image_data(chunk);
+        }
+        png
+    }
+
+    #[test]
+    fn test_gamma_round_trip() {
+        let gamma = Gamma::from_gamma(0.45455).unwrap();
+        let chunk = gamma.to_chunk();
+        assert_eq!(chunk.data(), &45455u32.to_be_bytes());
+        assert_eq!(Gamma::try_from(&chunk).unwrap(), gamma);
+    }
+
+    #[test]
+    fn test_invalid_gamma() {
+        assert!(Gamma::from_gamma(0.0).is_err());
+        let chunk = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![0, 0, 1]);
+        assert!(Gamma::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_chromaticities_round_trip() {
+        let chromaticities = Chromaticities::new(SRGB_CHROMATICITIES);
+        let decoded = Chromaticities::try_from(&chromaticities.to_chunk()).unwrap();
+        assert_eq!(decoded, chromaticities);
+        assert!(decoded.is_srgb());
+        assert_eq!(decoded.white_point(), (0.3127, 0.329));
+    }
+
+    #[test]
+    fn test_rendering_intent_round_trip() {
+        let chunk = RenderingIntent::Saturation.to_chunk();
+        assert_eq!(chunk.data(), &[2]);
+        assert_eq!(
+            RenderingIntent::try_from(&chunk).unwrap(),
+            RenderingIntent::Saturation
+        );
+        let chunk = Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![4]);
+        assert!(RenderingIntent::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_cicp_validation() {
+        assert!(Cicp::from_values(&[1, 13, 0, 1]).is_ok());
+        assert!(Cicp::from_values(&[1, 13, 1, 1]).is_err());
+        assert!(Cicp::from_values(&[1, 13, 0, 2]).is_err());
+        assert!(Cicp::from_values(&[1, 13, 0]).is_err());
+    }
+
+    #[test]
+    fn test_srgb_with_inconsistent_gamma() {
+        let png = testing_png(vec![
+            RenderingIntent::Perceptual.to_chunk(),
+            Gamma::from_gamma(1.0).unwrap().to_chunk(),
+        ]);
+        let color_space = ColorSpace::from_png(&png);
+        assert_eq!(color_space.conflicts().len(), 1);
+        assert_eq!(color_space.effective(), "sRGB (perceptual)");
+    }
+
+    #[test]
+    fn test_srgb_with_consistent_chunks() {
+        let png = testing_png(vec![
+            RenderingIntent::Perceptual.to_chunk(),
+            Gamma::new(SRGB_GAMMA).unwrap().to_chunk(),
+            Chromaticities::new(SRGB_CHROMATICITIES).to_chunk(),
+        ]);
+        assert!(ColorSpace::from_png(&png).conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_cicp_takes_precedence() {
+        let png = testing_png(vec![
+            RenderingIntent::Perceptual.to_chunk(),
+            Cicp::from_values(&[9, 16, 0, 1]).unwrap().to_chunk(),
+        ]);
+        let color_space = ColorSpace::from_png(&png);
+        assert_eq!(
+            color_space.effective(),
+            "cICP (BT.2020 primaries, PQ transfer)"
+        );
+    }
+
+    #[test]
+    fn test_color_chunk_after_idat() {
+        let mut png = testing_png(vec![]);
+        png.append_chunk(Gamma::new(SRGB_GAMMA).unwrap().to_chunk());
+        assert_eq!(ColorSpace::from_png(&png).conflicts().len(), 1);
+    }
+}
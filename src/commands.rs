@@ -1,8 +1,31 @@
+use crate::apng::{Animation, BlendOp, DisposeOp, FrameSettings};
+use crate::attachment::{self, Attachment};
+use crate::carve;
 use crate::chunk_type::ChunkType;
+use crate::codec;
+use crate::color::{Chromaticities, Cicp, ColorSpace, Gamma, RenderingIntent};
+use crate::compression::{self, Algorithm};
+use crate::crypto::{self, PublicKey, SecretKey};
+use crate::ihdr::ImageHeader;
+use crate::inspect::Inspection;
+use crate::json;
+use crate::output::{Format, OutputMode};
+use crate::palette::PaletteInfo;
+use crate::payload::Payload;
+use crate::phys::{PhysicalDimensions, Unit};
+use crate::repair;
+use crate::signature::{self, Signature, SigningKey, Verification, VerifyingKey};
+use crate::split;
+use crate::trailing::Trailing;
+use crate::validate;
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::io::{Read, Seek, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path;
+use std::str::FromStr;
+
+// Stands for stdin when reading and stdout when writing
+const STDIO: &str = "-";
 
 #[derive(Parser, Debug)]
 pub struct Cli {
@@ -16,28 +39,228 @@ enum Command {
         #[arg(short, long)]
         file_path: path::PathBuf,
         #[arg(short, long)]
-        chunk_type: String,
-        #[arg(short, long)]
-        message: String,
+        chunk_type: Option<String>,
+        #[arg(long, conflicts_with = "chunk_type")]
+        type_seed: Option<String>,
+        #[arg(short, long, required_unless_present = "input_file")]
+        message: Option<String>,
+        #[arg(short, long, conflicts_with = "message")]
+        input_file: Option<path::PathBuf>,
         #[arg(short, long)]
         output_file: Option<path::PathBuf>,
+        #[arg(short, long, conflicts_with = "recipients")]
+        passphrase: Option<String>,
+        #[arg(long, conflicts_with_all = ["passphrase", "recipients"])]
+        passphrase_file: Option<path::PathBuf>,
+        #[arg(short, long = "recipient")]
+        recipients: Vec<String>,
+        #[arg(long)]
+        sign_key: Option<path::PathBuf>,
+        #[arg(long, requires = "sign_key")]
+        sign_image: bool,
+        #[arg(long, value_enum)]
+        compress: Option<Algorithm>,
+        #[arg(long, default_value_t = split::DEFAULT_MAX_CHUNK_SIZE)]
+        max_chunk_size: usize,
     },
     Decode {
         #[arg(short, long)]
         file_path: path::PathBuf,
+        #[arg(short, long, required_unless_present = "type_seed")]
+        chunk_type: Option<String>,
+        #[arg(long, conflicts_with = "chunk_type")]
+        type_seed: Option<String>,
+        #[arg(short, long, conflicts_with = "identity")]
+        passphrase: Option<String>,
+        #[arg(long, conflicts_with_all = ["passphrase", "identity"])]
+        passphrase_file: Option<path::PathBuf>,
+        #[arg(short, long)]
+        identity: Option<path::PathBuf>,
+        #[arg(short = 'm', long, value_enum, default_value_t = OutputMode::Text)]
+        output_mode: OutputMode,
+        #[arg(short, long)]
+        output_file: Option<path::PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Text, conflicts_with_all = ["output_mode", "output_file"])]
+        format: Format,
+        #[arg(long)]
+        lenient: bool,
+    },
+    Extract {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short, long, required_unless_present = "type_seed")]
+        chunk_type: Option<String>,
+        #[arg(long, conflicts_with = "chunk_type")]
+        type_seed: Option<String>,
+        #[arg(short, long, conflicts_with = "identity")]
+        passphrase: Option<String>,
+        #[arg(long, conflicts_with_all = ["passphrase", "identity"])]
+        passphrase_file: Option<path::PathBuf>,
+        #[arg(short, long)]
+        identity: Option<path::PathBuf>,
+        #[arg(short, long)]
+        output_dir: Option<path::PathBuf>,
+        #[arg(long)]
+        lenient: bool,
+        #[arg(long)]
+        force: bool,
+    },
+    Keygen {
+        #[arg(short, long)]
+        output_file: path::PathBuf,
+        #[arg(long)]
+        signing: bool,
+        #[arg(long)]
+        force: bool,
+    },
+    Verify {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short, long)]
+        chunk_type: Option<String>,
         #[arg(short, long)]
-        chunk_type: String,
+        key: Option<String>,
     },
     Remove {
         #[arg(short, long)]
         file_path: path::PathBuf,
+        #[arg(short, long, required_unless_present = "type_seed")]
+        chunk_type: Option<String>,
+        #[arg(long, conflicts_with = "chunk_type")]
+        type_seed: Option<String>,
         #[arg(short, long)]
-        chunk_type: String,
+        output_file: Option<path::PathBuf>,
     },
     Print {
         #[arg(short, long)]
         file_path: path::PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
     },
+    Inspect {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(long)]
+        no_color: bool,
+    },
+    Validate {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    Trailing {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short = 'x', long, conflicts_with = "strip")]
+        extract: Option<path::PathBuf>,
+        #[arg(long)]
+        strip: bool,
+        #[arg(short, long, requires = "strip")]
+        output_file: Option<path::PathBuf>,
+    },
+    Carve {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short, long, conflicts_with = "list")]
+        output_dir: Option<path::PathBuf>,
+        #[arg(long)]
+        list: bool,
+    },
+    Repair {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short, long)]
+        output_file: Option<path::PathBuf>,
+        #[arg(short, long)]
+        report: Option<path::PathBuf>,
+        #[arg(long)]
+        keep_trailing: bool,
+    },
+    Color {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(long)]
+        gamma: Option<f64>,
+        #[arg(long, value_delimiter = ',')]
+        chromaticities: Option<Vec<f64>>,
+        #[arg(long)]
+        srgb: Option<RenderingIntent>,
+        #[arg(long, value_delimiter = ',')]
+        cicp: Option<Vec<u8>>,
+        #[arg(long)]
+        strip: bool,
+        #[arg(short, long)]
+        output_file: Option<path::PathBuf>,
+    },
+    Dpi {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(long, conflicts_with = "ppm")]
+        dpi: Option<f64>,
+        #[arg(long)]
+        ppm: Option<u32>,
+        #[arg(short, long)]
+        output_file: Option<path::PathBuf>,
+    },
+    Frames {
+        #[arg(short, long)]
+        file_path: path::PathBuf,
+        #[arg(short, long)]
+        output_dir: Option<path::PathBuf>,
+    },
+    Animate {
+        #[arg(short, long, num_args = 1.., required = true)]
+        files: Vec<path::PathBuf>,
+        #[arg(short, long, default_value_t = 10.0)]
+        rate: f64,
+        #[arg(long, value_delimiter = ',')]
+        delays: Option<Vec<u16>>,
+        #[arg(long, default_value_t = 0)]
+        plays: u32,
+        #[arg(long, value_enum, default_value_t = DisposeOp::None)]
+        dispose: DisposeOp,
+        #[arg(long, value_enum, default_value_t = BlendOp::Source)]
+        blend: BlendOp,
+        #[arg(short, long)]
+        output_file: path::PathBuf,
+    },
+}
+
+enum Protection {
+    None,
+    Passphrase(String),
+    Recipients(Vec<PublicKey>),
+}
+
+enum Unlock {
+    Passphrase(String),
+    SecretKey(SecretKey),
+}
+
+struct EncodeOptions {
+    compress: Option<Algorithm>,
+    max_chunk_size: usize,
+    protection: Protection,
+    signing: Option<(SigningKey, bool)>,
+}
+
+struct ColorEdits {
+    gamma: Option<f64>,
+    chromaticities: Option<Vec<f64>>,
+    srgb: Option<RenderingIntent>,
+    cicp: Option<Vec<u8>>,
+    strip: bool,
+}
+
+impl ColorEdits {
+    fn is_empty(&self) -> bool {
+        self.gamma.is_none()
+            && self.chromaticities.is_none()
+            && self.srgb.is_none()
+            && self.cicp.is_none()
+            && !self.strip
+    }
 }
 
 impl Cli {
@@ -48,18 +271,208 @@ impl Cli {
             Command::Encode {
                 file_path,
                 chunk_type,
+                type_seed,
                 message,
+                input_file,
                 output_file,
-            } => Cli::encode(file_path, chunk_type.try_into()?, &message, output_file)?,
+                passphrase,
+                passphrase_file,
+                recipients,
+                sign_key,
+                sign_image,
+                compress,
+                max_chunk_size,
+            } => {
+                let reads_stdin = message.as_deref() == Some(STDIO)
+                    || input_file.as_deref().is_some_and(Cli::is_stdio);
+                if reads_stdin && Cli::is_stdio(&file_path) {
+                    return Err("Only one of the PNG and the message can be read from stdin".into());
+                }
+                let payload = match (message, input_file) {
+                    (_, Some(input_file)) if Cli::is_stdio(&input_file) => {
+                        Payload::File(Attachment::new(
+                            "stdin",
+                            attachment::guess_mime_type(""),
+                            Cli::read_stdin()?,
+                        )?)
+                    }
+                    (_, Some(input_file)) => Payload::File(Attachment::from_file(&input_file)?),
+                    (Some(message), None) if message == STDIO => {
+                        Payload::Message(Cli::read_stdin()?)
+                    }
+                    (Some(message), None) => Payload::Message(message.into_bytes()),
+                    (None, None) => unreachable!("clap requires a message or an input file"),
+                };
+                let chunk_type = Cli::encode_chunk_type(chunk_type, type_seed)?;
+                let passphrase = Cli::passphrase(passphrase, passphrase_file)?;
+                let protection = Cli::protection(passphrase, recipients)?;
+                let signing = match sign_key {
+                    Some(sign_key) => Some((
+                        SigningKey::from_str(&fs::read_to_string(sign_key)?)?,
+                        sign_image,
+                    )),
+                    None => None,
+                };
+                let options = EncodeOptions {
+                    compress,
+                    max_chunk_size,
+                    protection,
+                    signing,
+                };
+                Cli::encode(
+                    file_path,
+                    chunk_type,
+                    payload.into_bytes(),
+                    output_file,
+                    options,
+                )?
+            }
             Command::Decode {
                 file_path,
                 chunk_type,
-            } => Cli::decode(file_path, &chunk_type)?,
+                type_seed,
+                passphrase,
+                passphrase_file,
+                identity,
+                output_mode,
+                output_file,
+                format,
+                lenient,
+            } => {
+                let chunk_type = Cli::chunk_type_name(chunk_type, type_seed);
+                let passphrase = Cli::passphrase(passphrase, passphrase_file)?;
+                let unlock = Cli::unlock(passphrase, identity)?;
+                let png = Cli::read_png_with(&file_path, lenient)?;
+                match format {
+                    Format::Json => Cli::decode_json(&png, &chunk_type, unlock)?,
+                    Format::Text => {
+                        Cli::decode(&png, &chunk_type, unlock, output_mode, output_file)?
+                    }
+                }
+            }
+            Command::Extract {
+                file_path,
+                chunk_type,
+                type_seed,
+                passphrase,
+                passphrase_file,
+                identity,
+                output_dir,
+                lenient,
+                force,
+            } => Cli::extract(
+                &Cli::read_png_with(&file_path, lenient)?,
+                &Cli::chunk_type_name(chunk_type, type_seed),
+                Cli::unlock(Cli::passphrase(passphrase, passphrase_file)?, identity)?,
+                output_dir,
+                force,
+            )?,
+            Command::Keygen {
+                output_file,
+                signing,
+                force,
+            } => {
+                if signing {
+                    Cli::keygen_signing(output_file, force)?
+                } else {
+                    Cli::keygen(output_file, force)?
+                }
+            }
+            Command::Verify {
+                file_path,
+                chunk_type,
+                key,
+            } => {
+                let trusted = match key {
+                    Some(key) => Some(match VerifyingKey::from_str(&key) {
+                        Ok(key) => key,
+                        Err(_) => VerifyingKey::from_str(&fs::read_to_string(&key)?)?,
+                    }),
+                    None => None,
+                };
+                Cli::verify(file_path, chunk_type, trusted)?
+            }
             Command::Remove {
                 file_path,
                 chunk_type,
-            } => Cli::remove(file_path, &chunk_type)?,
-            Command::Print { file_path } => Cli::print(file_path)?,
+                type_seed,
+                output_file,
+            } => Cli::remove(
+                file_path,
+                &Cli::chunk_type_name(chunk_type, type_seed),
+                output_file,
+            )?,
+            Command::Print { file_path, format } => Cli::print(file_path, format)?,
+            Command::Inspect {
+                file_path,
+                no_color,
+            } => Cli::inspect(file_path, no_color)?,
+            Command::Validate { file_path, format } => Cli::validate(file_path, format)?,
+            Command::Trailing {
+                file_path,
+                extract,
+                strip,
+                output_file,
+            } => Cli::trailing(file_path, extract, strip, output_file)?,
+            Command::Carve {
+                file_path,
+                output_dir,
+                list,
+            } => Cli::carve(file_path, output_dir, list)?,
+            Command::Repair {
+                file_path,
+                output_file,
+                report,
+                keep_trailing,
+            } => Cli::repair(file_path, output_file, report, keep_trailing)?,
+            Command::Color {
+                file_path,
+                gamma,
+                chromaticities,
+                srgb,
+                cicp,
+                strip,
+                output_file,
+            } => {
+                let edits = ColorEdits {
+                    gamma,
+                    chromaticities,
+                    srgb,
+                    cicp,
+                    strip,
+                };
+                Cli::color(file_path, edits, output_file)?
+            }
+            Command::Dpi {
+                file_path,
+                dpi,
+                ppm,
+                output_file,
+            } => Cli::dpi(file_path, dpi, ppm, output_file)?,
+            Command::Frames {
+                file_path,
+                output_dir,
+            } => Cli::frames(file_path, output_dir)?,
+            Command::Animate {
+                files,
+                rate,
+                delays,
+                plays,
+                dispose,
+                blend,
+                output_file,
+            } => {
+                let delays = Cli::frame_delays(files.len(), rate, delays)?;
+                let settings = delays
+                    .into_iter()
+                    .map(|delay_ms| FrameSettings {
+                        dispose_op: dispose,
+                        blend_op: blend,
+                        ..FrameSettings::from_delay_ms(delay_ms)
+                    })
+                    .collect();
+                Cli::animate(files, settings, plays, output_file)?
+            }
         }
         Ok(())
     }
@@ -67,68 +480,905 @@ impl Cli {
     fn encode(
         png_file: path::PathBuf,
         chunk_type: ChunkType,
-        message: &str,
+        payload: Vec<u8>,
         output_file: Option<path::PathBuf>,
+        options: EncodeOptions,
     ) -> crate::Result<()> {
-        let mut options = fs::OpenOptions::new();
-        let mut png_file = options.read(true).write(true).open(png_file)?;
-        let mut png_bytes: Vec<u8> = Vec::new();
-        png_file.read_to_end(&mut png_bytes)?;
-        let mut png = crate::png::Png::try_from(png_bytes.as_slice())?;
-        let chunk =
-            crate::chunk::Chunk::new(chunk_type, message.as_bytes().iter().copied().collect());
-        png.append_chunk(chunk);
-
-        return if let None = output_file {
-            png_file.rewind()?;
-            Ok(png_file.write_all(&png.as_bytes())?)
+        let mut png = Cli::read_png(&png_file)?;
+        Cli::append_payload(&mut png, &chunk_type, payload, options)?;
+        Cli::write_png(&png, output_file.unwrap_or(png_file))
+    }
+
+    fn append_payload(
+        png: &mut crate::png::Png,
+        chunk_type: &ChunkType,
+        payload: Vec<u8>,
+        options: EncodeOptions,
+    ) -> crate::Result<()> {
+        // Compress before encrypting, ciphertext doesn't compress
+        let payload = match options.compress {
+            Some(algorithm) => compression::compress(&payload, algorithm)?,
+            None => payload,
+        };
+        let data = match options.protection {
+            Protection::None => payload,
+            Protection::Passphrase(passphrase) => crypto::encrypt(&payload, &passphrase)?,
+            Protection::Recipients(recipients) => {
+                crypto::encrypt_to_recipients(&payload, &recipients)?
+            }
+        };
+        let parts = if data.len() > options.max_chunk_size {
+            split::split(&data, options.max_chunk_size)?
         } else {
-            let mut option = fs::OpenOptions::new();
-            let mut output_file = option.write(true).create(true).open(output_file.unwrap())?;
-            Ok(output_file.write_all(&png.as_bytes())?)
-        };
-    }
-
-    fn decode(png_file: path::PathBuf, chunk_type: &str) -> crate::Result<()> {
-        let mut file = fs::File::open(png_file)?;
-        let mut png_bytes = Vec::new();
-        file.read_to_end(&mut png_bytes)?;
-        let png = crate::png::Png::try_from(png_bytes.as_slice())?;
-        let chunk = png.chunk_by_type(chunk_type);
-        match chunk {
-            None => println!("Message not found"),
-            Some(chunk) => {
-                if let Ok(msg) = chunk.data_as_string() {
-                    println!("Message: {msg}");
-                } else {
-                    println!("Message: {:?}", chunk.data());
+            vec![data]
+        };
+        for part in parts {
+            png.append_chunk(crate::chunk::Chunk::new(chunk_type.clone(), part));
+        }
+        if let Some((signing_key, covers_image)) = options.signing {
+            // Drop stale signatures of the same chunk so verification stays meaningful
+            png.retain_chunks(|chunk| {
+                !Signature::try_from(chunk).is_ok_and(|sig| sig.target() == chunk_type)
+            });
+            let signature = Signature::sign(png, chunk_type, &signing_key, covers_image)?;
+            png.append_chunk(signature.to_chunk());
+        }
+        Ok(())
+    }
+
+    // Supplied types must stay out of the way of decoders, generated ones always do
+    fn encode_chunk_type(
+        chunk_type: Option<String>,
+        type_seed: Option<String>,
+    ) -> crate::Result<ChunkType> {
+        let chunk_type = match (chunk_type, type_seed) {
+            (Some(chunk_type), _) => ChunkType::from_str(&chunk_type)?,
+            (None, Some(type_seed)) => ChunkType::from_seed(type_seed.as_bytes()),
+            (None, None) => {
+                let chunk_type = ChunkType::random_private();
+                eprintln!("Using generated chunk type {chunk_type}, pass it to decode");
+                return Ok(chunk_type);
+            }
+        };
+        if chunk_type.is_critical() {
+            return Err(format!(
+                "{chunk_type} is a critical chunk type, decoders would reject the image. Use a lowercase first letter"
+            )
+            .into());
+        }
+        if !chunk_type.is_reserved_bit_valid() {
+            return Err(format!(
+                "{chunk_type} has the reserved bit set, the third letter must be uppercase"
+            )
+            .into());
+        }
+        if chunk_type.is_public() {
+            eprintln!(
+                "Warning: {chunk_type} is a public chunk type and may clash with a registered one"
+            );
+        }
+        if !chunk_type.is_safe_to_copy() {
+            eprintln!("Warning: {chunk_type} is not safe to copy, editors may drop it");
+        }
+        Ok(chunk_type)
+    }
+
+    fn chunk_type_name(chunk_type: Option<String>, type_seed: Option<String>) -> String {
+        match (chunk_type, type_seed) {
+            (Some(chunk_type), _) => chunk_type,
+            (None, Some(type_seed)) => ChunkType::from_seed(type_seed.as_bytes()).to_string(),
+            (None, None) => unreachable!("clap requires a chunk type or a type seed"),
+        }
+    }
+
+    fn verify(
+        png_file: path::PathBuf,
+        chunk_type: Option<String>,
+        trusted: Option<VerifyingKey>,
+    ) -> crate::Result<()> {
+        let png = Cli::read_png(&png_file)?;
+        let mut targets: Vec<ChunkType> = Vec::new();
+        match chunk_type {
+            Some(chunk_type) => targets.push(ChunkType::from_str(&chunk_type)?),
+            None => {
+                for signature in signature::signatures(&png) {
+                    match signature {
+                        Ok(signature) if !targets.contains(signature.target()) => {
+                            targets.push(signature.target().clone())
+                        }
+                        Ok(_) => {}
+                        Err(e) => println!("{}: INVALID: {e}", Signature::CHUNK_TYPE),
+                    }
+                }
+            }
+        }
+        if targets.is_empty() {
+            return Err("No signatures found".into());
+        }
+
+        let mut failures = 0;
+        for target in targets {
+            let result = signature::verify(&png, &target, trusted.as_ref());
+            if !matches!(result, Verification::Valid(_)) {
+                failures += 1;
+            }
+            println!("{target}: {result}");
+        }
+        if failures > 0 && trusted.is_none() {
+            return Err("No trusted key given, signatures are unverified".into());
+        }
+        if failures > 0 {
+            return Err(format!("{failures} signature check(s) failed").into());
+        }
+        Ok(())
+    }
+
+    fn decode(
+        png: &crate::png::Png,
+        chunk_type: &str,
+        unlock: Option<Unlock>,
+        output_mode: OutputMode,
+        output_file: Option<path::PathBuf>,
+    ) -> crate::Result<()> {
+        let data = match Cli::read_payload(png, chunk_type, unlock)? {
+            None => {
+                eprintln!("Message not found");
+                return Ok(());
+            }
+            Some(data) => data,
+        };
+        // Byte exact modes emit the file contents, text mode describes the file
+        let data = match (Payload::from_bytes(data)?, output_mode) {
+            (Payload::File(attachment), OutputMode::Text) if output_file.is_none() => {
+                println!("File: {attachment}");
+                println!("Use the extract command to write it out");
+                return Ok(());
+            }
+            (Payload::File(attachment), _) => attachment.data().to_vec(),
+            (Payload::Message(data), OutputMode::Text) if output_file.is_none() => {
+                match codec::render(chunk_type, &data) {
+                    Some(rendered) => {
+                        println!("{chunk_type}: {}", rendered?);
+                        return Ok(());
+                    }
+                    None => data,
                 }
             }
+            (Payload::Message(data), _) => data,
+        };
+
+        match output_file {
+            Some(output_file) if !Cli::is_stdio(&output_file) => {
+                // Files get the bytes themselves unless another encoding was asked for
+                let output_mode = match output_mode {
+                    OutputMode::Text => OutputMode::Raw,
+                    other => other,
+                };
+                fs::write(output_file, output_mode.render(&data))?
+            }
+            _ => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&output_mode.render(&data))?;
+                stdout.flush()?
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_json(
+        png: &crate::png::Png,
+        chunk_type: &str,
+        unlock: Option<Unlock>,
+    ) -> crate::Result<()> {
+        let data = Cli::read_payload(png, chunk_type, unlock)?;
+        println!("{}", json::payload(chunk_type, data.as_deref())?);
+        Ok(())
+    }
+
+    fn extract(
+        png: &crate::png::Png,
+        chunk_type: &str,
+        unlock: Option<Unlock>,
+        output_dir: Option<path::PathBuf>,
+        force: bool,
+    ) -> crate::Result<()> {
+        let data = Cli::read_payload(png, chunk_type, unlock)?
+            .ok_or(format!("No {chunk_type} chunk found"))?;
+        let Payload::File(attachment) = Payload::from_bytes(data)? else {
+            return Err("Chunk holds a message, not a file. Use decode instead".into());
+        };
+        let output_dir = output_dir.unwrap_or_default();
+        if !output_dir.as_os_str().is_empty() {
+            fs::create_dir_all(&output_dir)?;
         }
+        // The name comes from the carrier file, it must not get to replace .bashrc
+        let output_file = output_dir.join(attachment.filename());
+        Cli::create_file(&output_file, attachment.data(), 0o666, force)?;
+        println!("{attachment}");
+        println!("Written to {}", output_file.display());
         Ok(())
     }
 
-    fn remove(png_file: path::PathBuf, chunk_type: &str) -> crate::Result<()> {
+    // Undoes encryption and compression, whichever were applied when encoding
+    fn read_payload(
+        png: &crate::png::Png,
+        chunk_type: &str,
+        unlock: Option<Unlock>,
+    ) -> crate::Result<Option<Vec<u8>>> {
+        let chunks: Vec<&[u8]> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() == chunk_type)
+            .map(|chunk| chunk.data())
+            .collect();
+        let payload = match chunks.first() {
+            None => return Ok(None),
+            Some(data) if split::is_part(data) => {
+                let parts: Vec<&[u8]> = chunks
+                    .into_iter()
+                    .filter(|data| split::is_part(data))
+                    .collect();
+                let reassembled = split::reassemble(&parts)?;
+                for issue in reassembled.issues() {
+                    eprintln!("Warning: {issue}");
+                }
+                reassembled.into_data()
+            }
+            Some(data) => data.to_vec(),
+        };
+
+        let data = match (crypto::is_encrypted(&payload), unlock) {
+            (true, Some(Unlock::Passphrase(passphrase))) => crypto::decrypt(&payload, &passphrase)?,
+            (true, Some(Unlock::SecretKey(secret_key))) => {
+                crypto::decrypt_with_key(&payload, &secret_key)?
+            }
+            (true, None) => {
+                return Err(
+                    "Message is encrypted, use --passphrase-file, --passphrase or --identity"
+                        .into(),
+                )
+            }
+            (false, Some(_)) => return Err("Message is not encrypted".into()),
+            (false, None) => payload,
+        };
+        if compression::is_compressed(&data) {
+            return Ok(Some(compression::decompress(&data)?));
+        }
+        Ok(Some(data))
+    }
+
+    // A passphrase on the command line shows up in ps and the shell history, a file doesn't
+    fn passphrase(
+        passphrase: Option<String>,
+        passphrase_file: Option<path::PathBuf>,
+    ) -> crate::Result<Option<String>> {
+        let Some(passphrase_file) = passphrase_file else {
+            return Ok(passphrase);
+        };
+        let contents = fs::read_to_string(&passphrase_file)?;
+        match contents.lines().next() {
+            Some(line) if !line.is_empty() => Ok(Some(line.to_string())),
+            _ => Err(format!("{} holds no passphrase", passphrase_file.display()).into()),
+        }
+    }
+
+    fn unlock(
+        passphrase: Option<String>,
+        identity: Option<path::PathBuf>,
+    ) -> crate::Result<Option<Unlock>> {
+        Ok(match identity {
+            Some(identity) => Some(Unlock::SecretKey(SecretKey::from_str(
+                &fs::read_to_string(identity)?,
+            )?)),
+            None => passphrase.map(Unlock::Passphrase),
+        })
+    }
+
+    // The private key is created with 0600 from the start, never readable by others in between
+    fn write_key_files(
+        output_file: &path::Path,
+        private: &str,
+        public: &str,
+        force: bool,
+    ) -> crate::Result<()> {
+        let mut public_file = output_file.to_path_buf().into_os_string();
+        public_file.push(".pub");
+        let public_file = path::PathBuf::from(public_file);
+        for file in [output_file, &public_file] {
+            if file.exists() && !force {
+                return Err(format!(
+                    "{} already exists, pass --force to overwrite it",
+                    file.display()
+                )
+                .into());
+            }
+        }
+
+        Cli::create_file(output_file, private.as_bytes(), 0o600, force)?;
+        Cli::create_file(&public_file, public.as_bytes(), 0o644, force)
+    }
+
+    // Never replaces an existing file unless forced, and then only after removing it so
+    // the old file's looser permissions are never kept
+    fn create_file(
+        file: &path::Path,
+        contents: &[u8],
+        mode: u32,
+        force: bool,
+    ) -> crate::Result<()> {
+        if force && file.exists() {
+            fs::remove_file(file)?;
+        }
         let mut options = fs::OpenOptions::new();
-        let mut png_file = options.read(true).write(true).open(png_file)?;
-        let mut png_bytes: Vec<u8> = Vec::new();
-        png_file.read_to_end(&mut png_bytes)?;
-        let mut png = crate::png::Png::try_from(png_bytes.as_slice())?;
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        let mut output = match options.open(file) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(format!(
+                    "{} already exists, pass --force to overwrite it",
+                    file.display()
+                )
+                .into())
+            }
+            result => result?,
+        };
+        output.write_all(contents)?;
+        Ok(())
+    }
 
-        if let Ok(_) = png.remove_chunk(chunk_type) {
-            println!("Chunk removed successfully");
-        } else {
+    fn keygen(output_file: path::PathBuf, force: bool) -> crate::Result<()> {
+        let secret_key = SecretKey::generate();
+        let public_key = secret_key.public_key();
+        Cli::write_key_files(
+            &output_file,
+            &format!("# public key: {public_key}\n{secret_key}\n"),
+            &format!("{public_key}\n"),
+            force,
+        )?;
+        println!("Private key written to {}", output_file.display());
+        println!("Public key: {public_key}");
+        Ok(())
+    }
+
+    fn keygen_signing(output_file: path::PathBuf, force: bool) -> crate::Result<()> {
+        let signing_key = SigningKey::generate();
+        let verifying_key = signing_key.verifying_key();
+        Cli::write_key_files(
+            &output_file,
+            &format!("# verifying key: {verifying_key}\n{signing_key}\n"),
+            &format!("{verifying_key}\n"),
+            force,
+        )?;
+        println!("Signing key written to {}", output_file.display());
+        println!("Verifying key: {verifying_key}");
+        Ok(())
+    }
+
+    // Recipients can be given inline or as the path of a public key file
+    fn protection(
+        passphrase: Option<String>,
+        recipients: Vec<String>,
+    ) -> crate::Result<Protection> {
+        if let Some(passphrase) = passphrase {
+            return Ok(Protection::Passphrase(passphrase));
+        }
+        if recipients.is_empty() {
+            return Ok(Protection::None);
+        }
+        let mut keys = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            let key = match PublicKey::from_str(&recipient) {
+                Ok(key) => key,
+                Err(_) => PublicKey::from_str(&fs::read_to_string(&recipient)?)?,
+            };
+            keys.push(key);
+        }
+        Ok(Protection::Recipients(keys))
+    }
+
+    fn remove(
+        png_file: path::PathBuf,
+        chunk_type: &str,
+        output_file: Option<path::PathBuf>,
+    ) -> crate::Result<()> {
+        let mut png = Cli::read_png(&png_file)?;
+        let output_file = output_file.unwrap_or(png_file);
+
+        // Split payloads span several chunks of the same type, leaving some behind would
+        // keep a payload that can no longer be reassembled
+        let before = png.chunks().len();
+        png.retain_chunks(|chunk| chunk.chunk_type().to_string() != chunk_type);
+        let removed = before - png.chunks().len();
+        if removed == 0 {
             eprintln!("Chunk not found");
+            return Ok(());
+        }
+        Cli::write_png(&png, output_file.clone())?;
+        let status = format!("Removed {removed} {chunk_type} chunk(s)");
+        if Cli::is_stdio(&output_file) {
+            eprintln!("{status}");
+        } else {
+            println!("{status}");
+        }
+        Ok(())
+    }
+
+    fn trailing(
+        png_file: path::PathBuf,
+        extract: Option<path::PathBuf>,
+        strip: bool,
+        output_file: Option<path::PathBuf>,
+    ) -> crate::Result<()> {
+        let mut png = Cli::read_png(&png_file)?;
+        let Some(trailing) = Trailing::analyze(&png) else {
+            eprintln!("No data after IEND");
+            return Ok(());
+        };
+        // Reports go to stderr whenever the data itself is headed for stdout
+        let to_stdout = extract.as_deref().is_some_and(Cli::is_stdio)
+            || (strip && output_file.as_deref().is_some_and(Cli::is_stdio));
+        if to_stdout {
+            eprint!("{trailing}");
+        } else {
+            print!("{trailing}");
+        }
+
+        if let Some(extract) = extract {
+            if Cli::is_stdio(&extract) {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(png.trailing())?;
+                stdout.flush()?;
+            } else {
+                fs::write(&extract, png.trailing())?;
+                println!("Written to {}", extract.display());
+            }
+        } else if strip {
+            let removed = png.strip_trailing();
+            let output_file = output_file.unwrap_or(png_file);
+            Cli::write_png(&png, output_file.clone())?;
+            if !Cli::is_stdio(&output_file) {
+                println!("Stripped {} bytes", removed.len());
+            }
+        }
+        Ok(())
+    }
+
+    fn carve(
+        blob_file: path::PathBuf,
+        output_dir: Option<path::PathBuf>,
+        list: bool,
+    ) -> crate::Result<()> {
+        let carved = carve::carve(&Cli::read_bytes(&blob_file)?);
+        if carved.is_empty() {
+            eprintln!("No PNGs found");
+            return Ok(());
+        }
+        let output_dir = output_dir.unwrap_or_default();
+        if !list && !output_dir.as_os_str().is_empty() {
+            fs::create_dir_all(&output_dir)?;
+        }
+        for png in &carved {
+            print!("{png}");
+            if list {
+                continue;
+            }
+            // Named after the source offset so results can be traced back into the blob
+            let output_file = output_dir.join(format!("carved-{:010}.png", png.offset()));
+            fs::write(&output_file, png.png().as_bytes())?;
+            println!("  Written to {}", output_file.display());
+        }
+        let complete = carved.iter().filter(|png| png.complete()).count();
+        println!(
+            "Found {} PNG(s), {complete} complete and {} partial",
+            carved.len(),
+            carved.len() - complete
+        );
+        Ok(())
+    }
+
+    fn repair(
+        png_file: path::PathBuf,
+        output_file: Option<path::PathBuf>,
+        report_file: Option<path::PathBuf>,
+        keep_trailing: bool,
+    ) -> crate::Result<()> {
+        let repair = repair::repair(&Cli::read_bytes(&png_file)?, keep_trailing)?;
+        let report = repair.to_string();
+        if let Some(report_file) = report_file {
+            fs::write(report_file, &report)?;
+        }
+        // Leave an intact file alone rather than rewriting it in place
+        let output_file = match output_file {
+            Some(output_file) => output_file,
+            None if repair.fixes().is_empty() => {
+                print!("{report}");
+                return Ok(());
+            }
+            None => png_file,
+        };
+        Cli::write_png(repair.png(), output_file.clone())?;
+        if Cli::is_stdio(&output_file) {
+            eprint!("{report}");
+        } else {
+            print!("{report}");
+        }
+        Ok(())
+    }
+
+    fn print(png_file: path::PathBuf, format: Format) -> crate::Result<()> {
+        let png = Cli::read_png(&png_file)?;
+        if format == Format::Json {
+            println!("{}", json::png(&png));
+            return Ok(());
+        }
+        let header = ImageHeader::from_png(&png).ok();
+        for chunk in png.chunks() {
+            print!("{chunk}");
+            if let Some(rendered) = codec::with_registry(|registry| registry.render_chunk(chunk)) {
+                match rendered {
+                    Ok(rendered) => println!("    {rendered}"),
+                    Err(e) => println!("    invalid, {e}"),
+                }
+            } else if let Some(info) = chunk.chunk_type().known_info() {
+                match info.pretty_print(chunk, header.as_ref()) {
+                    Some(Ok(pretty)) => println!("    {} ({}): {pretty}", info.name, info.spec),
+                    Some(Err(e)) => println!("    {} ({}): invalid, {e}", info.name, info.spec),
+                    None => println!("    {} ({})", info.name, info.spec),
+                }
+            }
+        }
+        println!();
+        if let Some(header) = header {
+            println!("Image: {header}");
+        }
+        print!("{}", ColorSpace::from_png(&png));
+        if let Some(chunk) = png.chunk_by_type(PhysicalDimensions::CHUNK_TYPE) {
+            match PhysicalDimensions::try_from(chunk) {
+                Ok(dimensions) => println!("Physical dimensions: {dimensions}"),
+                Err(e) => println!("Physical dimensions: invalid, {e}"),
+            }
+        }
+        if let Ok(palette_info) = PaletteInfo::from_png(&png) {
+            print!("{palette_info}");
+        }
+        if Animation::is_animated(&png) {
+            match Animation::from_png(&png) {
+                Ok(animation) => print!("{animation}"),
+                Err(e) => println!("Animation: invalid, {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    fn inspect(png_file: path::PathBuf, no_color: bool) -> crate::Result<()> {
+        let bytes = if Cli::is_stdio(&png_file) {
+            Cli::read_stdin()?
+        } else {
+            fs::read(&png_file)?
+        };
+        let inspection = Inspection::scan(&bytes)?;
+        // Honour https://no-color.org as well as the flag
+        let color =
+            !no_color && std::env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal();
+        print!("{}", inspection.render(color));
+        Ok(())
+    }
+
+    fn validate(png_file: path::PathBuf, format: Format) -> crate::Result<()> {
+        let (chunks, issues) = match Cli::read_png(&png_file) {
+            Ok(png) => (png.chunks().len(), validate::validate(&png)),
+            Err(e) => (0, vec![e.to_string()]),
+        };
+        match format {
+            Format::Json => println!(
+                "{}",
+                serde_json::json!({
+                    "schema_version": json::SCHEMA_VERSION,
+                    "valid": issues.is_empty(),
+                    "chunks": chunks,
+                    "issues": issues,
+                })
+            ),
+            Format::Text if issues.is_empty() => println!("Valid PNG with {chunks} chunks"),
+            Format::Text => {
+                for issue in &issues {
+                    println!("{issue}");
+                }
+            }
+        }
+        if !issues.is_empty() {
+            return Err(format!("Validation found {} issue(s)", issues.len()).into());
         }
         Ok(())
     }
 
-    fn print(png_file: path::PathBuf) -> crate::Result<()> {
-        let mut file = fs::File::open(png_file)?;
-        let mut png_bytes = Vec::new();
-        file.read_to_end(&mut png_bytes)?;
-        let png = crate::png::Png::try_from(png_bytes.as_slice())?;
-        println!("{png}");
+    fn color(
+        png_file: path::PathBuf,
+        edits: ColorEdits,
+        output_file: Option<path::PathBuf>,
+    ) -> crate::Result<()> {
+        let mut png = Cli::read_png(&png_file)?;
+        if edits.is_empty() {
+            print!("{}", ColorSpace::from_png(&png));
+            return Ok(());
+        }
+
+        if edits.strip {
+            for chunk_type in ColorSpace::CHUNK_TYPES {
+                while png.remove_chunk(chunk_type).is_ok() {}
+            }
+        }
+        if let Some(gamma) = edits.gamma {
+            png.set_chunk(Gamma::from_gamma(gamma)?.to_chunk());
+        }
+        if let Some(points) = edits.chromaticities {
+            png.set_chunk(Chromaticities::from_points(&points)?.to_chunk());
+        }
+        if let Some(intent) = edits.srgb {
+            png.set_chunk(intent.to_chunk());
+        }
+        if let Some(values) = edits.cicp {
+            png.set_chunk(Cicp::from_values(&values)?.to_chunk());
+        }
+
+        let color_space = ColorSpace::from_png(&png);
+        for conflict in color_space.conflicts() {
+            eprintln!("Warning: {conflict}");
+        }
+        Cli::write_png(&png, output_file.unwrap_or(png_file))
+    }
+
+    fn dpi(
+        png_file: path::PathBuf,
+        dpi: Option<f64>,
+        ppm: Option<u32>,
+        output_file: Option<path::PathBuf>,
+    ) -> crate::Result<()> {
+        let mut png = Cli::read_png(&png_file)?;
+        let phys = match (dpi, ppm) {
+            (Some(dpi), _) => PhysicalDimensions::from_dpi(dpi)?,
+            (None, Some(ppm)) => PhysicalDimensions::new(ppm, ppm, Unit::Meter),
+            (None, None) => {
+                match png.chunk_by_type(PhysicalDimensions::CHUNK_TYPE) {
+                    Some(chunk) => println!("{}", PhysicalDimensions::try_from(chunk)?),
+                    None => println!("No pHYs chunk present"),
+                }
+                return Ok(());
+            }
+        };
+
+        // pHYs has to come before the first IDAT chunk
+        png.set_chunk(phys.to_chunk());
+        let output_file = output_file.unwrap_or(png_file);
+        if Cli::is_stdio(&output_file) {
+            eprintln!("{phys}");
+        } else {
+            println!("{phys}");
+        }
+        Cli::write_png(&png, output_file)
+    }
+
+    fn frames(png_file: path::PathBuf, output_dir: Option<path::PathBuf>) -> crate::Result<()> {
+        let png = Cli::read_png(&png_file)?;
+        let animation = Animation::from_png(&png)?;
+        let output_dir = match output_dir {
+            None => {
+                print!("{animation}");
+                return Ok(());
+            }
+            Some(output_dir) => output_dir,
+        };
+
+        fs::create_dir_all(&output_dir)?;
+        for idx in 0..animation.frames().len() {
+            let frame_file = output_dir.join(format!("frame_{idx:03}.png"));
+            Cli::write_png(&animation.frame_png(idx)?, frame_file.clone())?;
+            println!("Frame {idx} written to {}", frame_file.display());
+        }
+        for issue in animation.issues() {
+            eprintln!("Warning: {issue}");
+        }
         Ok(())
     }
+
+    fn animate(
+        files: Vec<path::PathBuf>,
+        settings: Vec<FrameSettings>,
+        plays: u32,
+        output_file: path::PathBuf,
+    ) -> crate::Result<()> {
+        let mut frames = Vec::with_capacity(files.len());
+        for (file, settings) in files.iter().zip(settings) {
+            frames.push((Cli::read_png(file)?, settings));
+        }
+        let png = Animation::assemble(&frames, plays)?;
+        Cli::write_png(&png, output_file)
+    }
+
+    fn frame_delays(
+        num_frames: usize,
+        rate: f64,
+        delays: Option<Vec<u16>>,
+    ) -> crate::Result<Vec<u16>> {
+        let delays = match delays {
+            Some(delays) => delays,
+            None => {
+                if !rate.is_finite() || rate <= 0.0 {
+                    return Err(format!("Invalid frame rate: {rate}").into());
+                }
+                vec![(1000.0 / rate).round().min(u16::MAX as f64) as u16; num_frames]
+            }
+        };
+        if delays.len() != num_frames {
+            return Err(format!("{} delays given for {num_frames} frames", delays.len()).into());
+        }
+        Ok(delays)
+    }
+
+    fn is_stdio(path: &path::Path) -> bool {
+        path.as_os_str() == STDIO
+    }
+
+    fn read_stdin() -> crate::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_bytes(png_file: &path::Path) -> crate::Result<Vec<u8>> {
+        if Cli::is_stdio(png_file) {
+            Cli::read_stdin()
+        } else {
+            Ok(fs::read(png_file)?)
+        }
+    }
+
+    fn read_png(png_file: &path::Path) -> crate::Result<crate::png::Png> {
+        crate::png::Png::try_from(Cli::read_bytes(png_file)?.as_slice())
+    }
+
+    // Lenient reads warn about damaged chunks on stderr and go on with what was recovered
+    fn read_png_with(png_file: &path::Path, lenient: bool) -> crate::Result<crate::png::Png> {
+        if !lenient {
+            return Cli::read_png(png_file);
+        }
+        let (png, issues) = crate::png::Png::parse_lenient(
+            &Cli::read_bytes(png_file)?,
+            crate::png::Png::DEFAULT_MAX_ALLOC,
+        )?;
+        for issue in issues {
+            eprintln!("Warning: {issue}");
+        }
+        Ok(png)
+    }
+
+    fn write_png(png: &crate::png::Png, output_file: path::PathBuf) -> crate::Result<()> {
+        if Cli::is_stdio(&output_file) {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&png.as_bytes())?;
+            return Ok(stdout.flush()?);
+        }
+        Ok(fs::write(output_file, png.as_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::png::Png;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_extract_does_not_overwrite_existing_file() {
+        let mut png = testing_png();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let attachment = Attachment::new("notes.txt", "text/plain", b"from the png".to_vec());
+        let options = EncodeOptions {
+            compress: None,
+            max_chunk_size: split::DEFAULT_MAX_CHUNK_SIZE,
+            protection: Protection::None,
+            signing: None,
+        };
+        let payload = Payload::File(attachment.unwrap()).into_bytes();
+        Cli::append_payload(&mut png, &chunk_type, payload, options).unwrap();
+
+        let output_dir = std::env::temp_dir().join(format!("pngme-extract-{}", std::process::id()));
+        fs::create_dir_all(&output_dir).unwrap();
+        let existing = output_dir.join("notes.txt");
+        fs::write(&existing, b"keep me").unwrap();
+        let result = Cli::extract(&png, "ruSt", None, Some(output_dir.clone()), false);
+        let kept = fs::read(&existing).unwrap();
+        Cli::extract(&png, "ruSt", None, Some(output_dir.clone()), true).unwrap();
+        let forced = fs::read(&existing).unwrap();
+        fs::remove_dir_all(&output_dir).unwrap();
+
+        assert!(result.unwrap_err().to_string().contains("already exists"));
+        assert_eq!(kept, b"keep me");
+        assert_eq!(forced, b"from the png");
+    }
+
+    #[test]
+    fn test_passphrase_file_takes_the_first_line() {
+        let file = std::env::temp_dir().join(format!("pngme-passphrase-{}", std::process::id()));
+        fs::write(&file, "correct horse\r\nsecond line\n").unwrap();
+        let passphrase = Cli::passphrase(None, Some(file.clone()));
+        fs::write(&file, "\n").unwrap();
+        let empty = Cli::passphrase(None, Some(file.clone()));
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(passphrase.unwrap().as_deref(), Some("correct horse"));
+        assert!(empty.is_err());
+        assert_eq!(
+            Cli::passphrase(Some("inline".into()), None)
+                .unwrap()
+                .as_deref(),
+            Some("inline")
+        );
+    }
+
+    #[test]
+    fn test_remove_drops_every_split_part() {
+        let mut png = testing_png();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let options = EncodeOptions {
+            compress: None,
+            max_chunk_size: split::HEADER_LEN + 4,
+            protection: Protection::None,
+            signing: None,
+        };
+        let payload =
+            Payload::Message(b"a message split across several chunks".to_vec()).into_bytes();
+        Cli::append_payload(&mut png, &chunk_type, payload, options).unwrap();
+        assert!(png.chunks().len() > 3);
+
+        let png_file =
+            std::env::temp_dir().join(format!("pngme-remove-{}.png", std::process::id()));
+        fs::write(&png_file, png.as_bytes()).unwrap();
+        Cli::remove(png_file.clone(), "ruSt", None).unwrap();
+        let png = Png::try_from(fs::read(&png_file).unwrap().as_slice()).unwrap();
+        fs::remove_file(&png_file).unwrap();
+
+        assert_eq!(png.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_escaped_message_survives_compression_encryption_and_splitting() {
+        // Starts like a compressed payload, which is what read_payload sees after decrypting
+        let mut message = compression::MAGIC.to_vec();
+        message.extend(b"\x01 is just the start of this message");
+        let secret_key = SecretKey::generate();
+        let mut png = testing_png();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let options = EncodeOptions {
+            compress: Some(Algorithm::Deflate),
+            max_chunk_size: split::HEADER_LEN + 16,
+            protection: Protection::Recipients(vec![secret_key.public_key()]),
+            signing: None,
+        };
+        let payload = Payload::Message(message.clone()).into_bytes();
+        Cli::append_payload(&mut png, &chunk_type, payload, options).unwrap();
+        assert!(png.chunks().len() > 3);
+
+        let unlock = Unlock::SecretKey(SecretKey::from_str(&secret_key.to_string()).unwrap());
+        let data = Cli::read_payload(&png, "ruSt", Some(unlock))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Payload::from_bytes(data).unwrap(),
+            Payload::Message(message)
+        );
+    }
 }
@@ -0,0 +1,187 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+// Layout: MAGIC | version | algorithm | original length (u32) | compressed bytes
+pub const MAGIC: [u8; 4] = *b"PNGZ";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 4;
+// Refuse to inflate past this so a tiny chunk can't expand into gigabytes
+const MAX_DECOMPRESSED_LEN: u32 = 1 << 28;
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum Algorithm {
+    Deflate,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Algorithm {
+    fn id(&self) -> u8 {
+        match self {
+            Self::Deflate => 1,
+            #[cfg(feature = "zstd")]
+            Self::Zstd => 2,
+            #[cfg(feature = "brotli")]
+            Self::Brotli => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> crate::Result<Self> {
+        match id {
+            1 => Ok(Self::Deflate),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Self::Zstd),
+            #[cfg(feature = "brotli")]
+            3 => Ok(Self::Brotli),
+            #[cfg(not(feature = "zstd"))]
+            2 => Err("zstd support was not compiled in, rebuild with --features zstd".into()),
+            #[cfg(not(feature = "brotli"))]
+            3 => Err("brotli support was not compiled in, rebuild with --features brotli".into()),
+            other => Err(format!("Unknown compression algorithm: {other}").into()),
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Deflate => "deflate",
+            #[cfg(feature = "zstd")]
+            Self::Zstd => "zstd",
+            #[cfg(feature = "brotli")]
+            Self::Brotli => "brotli",
+        };
+        write!(f, "{name}")
+    }
+}
+
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+pub fn compress(data: &[u8], algorithm: Algorithm) -> crate::Result<Vec<u8>> {
+    let original_len = u32::try_from(data.len())
+        .ok()
+        .filter(|len| *len <= MAX_DECOMPRESSED_LEN)
+        .ok_or("Message is too large to compress")?;
+    let mut output = MAGIC.to_vec();
+    output.extend([VERSION, algorithm.id()]);
+    output.extend(original_len.to_be_bytes());
+
+    match algorithm {
+        Algorithm::Deflate => {
+            let mut encoder = DeflateEncoder::new(output, flate2::Compression::best());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Algorithm::Zstd => {
+            output.extend(zstd::encode_all(data, 19)?);
+            Ok(output)
+        }
+        #[cfg(feature = "brotli")]
+        Algorithm::Brotli => {
+            let mut encoder = brotli::CompressorWriter::new(output, 4096, 11, 22);
+            encoder.write_all(data)?;
+            Ok(encoder.into_inner())
+        }
+    }
+}
+
+pub fn algorithm(data: &[u8]) -> crate::Result<Algorithm> {
+    if !is_compressed(data) || data.len() < HEADER_LEN {
+        return Err("Payload is not compressed".into());
+    }
+    let version = data[MAGIC.len()];
+    if version != VERSION {
+        return Err(format!("Unsupported compression version: {version}").into());
+    }
+    Algorithm::from_id(data[MAGIC.len() + 1])
+}
+
+pub fn decompress(data: &[u8]) -> crate::Result<Vec<u8>> {
+    let algorithm = algorithm(data)?;
+    let original_len = u32::from_be_bytes(data[MAGIC.len() + 2..HEADER_LEN].try_into()?);
+    if original_len > MAX_DECOMPRESSED_LEN {
+        return Err(format!("Declared message length {original_len} exceeds the limit").into());
+    }
+    let body = &data[HEADER_LEN..];
+    let reader: Box<dyn Read + '_> = match algorithm {
+        Algorithm::Deflate => Box::new(DeflateDecoder::new(body)),
+        #[cfg(feature = "zstd")]
+        Algorithm::Zstd => Box::new(zstd::Decoder::new(body)?),
+        #[cfg(feature = "brotli")]
+        Algorithm::Brotli => Box::new(brotli::Decompressor::new(body, 4096)),
+    };
+
+    // Read one byte past the declared length to notice payloads that lie about their size
+    let mut output = Vec::with_capacity(original_len as usize);
+    reader
+        .take(original_len as u64 + 1)
+        .read_to_end(&mut output)?;
+    if output.len() != original_len as usize {
+        return Err(format!(
+            "Decompressed length mismatch. Expected {original_len}, found {}",
+            output.len()
+        )
+        .into());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    fn testing_message() -> Vec<u8> {
+        br#"{"message": "This is where your secret message will be!"}"#.repeat(50)
+    }
+
+    #[test]
+    fn test_round_trip_all_algorithms() {
+        let message = testing_message();
+        for algorithm in Algorithm::value_variants() {
+            let compressed = compress(&message, *algorithm).unwrap();
+            assert!(is_compressed(&compressed));
+            assert!(compressed.len() < message.len());
+            assert_eq!(super::algorithm(&compressed).unwrap(), *algorithm);
+            assert_eq!(decompress(&compressed).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let mut compressed = compress(&testing_message(), Algorithm::Deflate).unwrap();
+        compressed[MAGIC.len() + 5] -= 1;
+        assert!(decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_declared_length_limit() {
+        let mut compressed = compress(b"tiny", Algorithm::Deflate).unwrap();
+        compressed[MAGIC.len() + 2..HEADER_LEN].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_unknown_algorithm() {
+        let mut compressed = compress(b"tiny", Algorithm::Deflate).unwrap();
+        compressed[MAGIC.len() + 1] = 42;
+        assert!(decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_compress_then_encrypt() {
+        let message = testing_message();
+        let compressed = compress(&message, Algorithm::Deflate).unwrap();
+        let params = crate::crypto::KdfParams::new(64, 1, 1);
+        let encrypted = crate::crypto::encrypt_with_params(&compressed, "hunter2", params).unwrap();
+        let decrypted = crate::crypto::decrypt(&encrypted, "hunter2").unwrap();
+        assert_eq!(decompress(&decrypted).unwrap(), message);
+    }
+}
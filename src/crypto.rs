@@ -0,0 +1,458 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use std::fmt::Display;
+use std::str::FromStr;
+use x25519_dalek::StaticSecret;
+
+// Layout: MAGIC | version | mode | mode specific header | nonce | ciphertext + tag
+pub const MAGIC: [u8; 4] = *b"PNGE";
+const VERSION: u8 = 1;
+const MODE_PASSPHRASE: u8 = 1;
+const MODE_RECIPIENTS: u8 = 2;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+const KDF_PARAMS_LEN: usize = 9;
+// Upper bounds so a crafted header can't make decoding allocate gigabytes or spin for minutes
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u8 = 16;
+
+const PUBLIC_KEY_PREFIX: &str = "pngme-public-key:";
+const SECRET_KEY_PREFIX: &str = "pngme-secret-key:";
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+const MAX_RECIPIENTS: usize = u8::MAX as usize;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u8,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST as u8,
+        }
+    }
+}
+
+impl KdfParams {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u8) -> Self {
+        Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        }
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        self.memory_kib
+            .to_be_bytes()
+            .iter()
+            .chain(self.iterations.to_be_bytes().iter())
+            .chain([self.parallelism].iter())
+            .copied()
+            .collect()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let params = Self {
+            memory_kib: u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            iterations: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            parallelism: bytes[8],
+        };
+        params.check_limits()?;
+        Ok(params)
+    }
+
+    fn check_limits(&self) -> crate::Result<()> {
+        if self.memory_kib > MAX_MEMORY_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(format!(
+                "Key derivation parameters exceed the allowed limits of {} MiB, {MAX_ITERATIONS} iterations and {MAX_PARALLELISM} lanes",
+                MAX_MEMORY_KIB / 1024
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> crate::Result<[u8; KEY_LEN]> {
+        self.check_limits()?;
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism as u32,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| format!("Invalid key derivation parameters: {e}"))?;
+        let mut key = [0u8; KEY_LEN];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("Key derivation failed: {e}"))?;
+        Ok(key)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PublicKey {
+    key: x25519_dalek::PublicKey,
+}
+
+impl PublicKey {
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        self.key.as_bytes()
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            key: parse_key(s, PUBLIC_KEY_PREFIX)?.into(),
+        })
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{PUBLIC_KEY_PREFIX}{}", hex::encode(self.key.as_bytes()))
+    }
+}
+
+pub struct SecretKey {
+    key: StaticSecret,
+}
+
+impl SecretKey {
+    pub fn generate() -> Self {
+        Self {
+            key: StaticSecret::random_from_rng(OsRng),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            key: (&self.key).into(),
+        }
+    }
+}
+
+impl FromStr for SecretKey {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            key: parse_key(s, SECRET_KEY_PREFIX)?.into(),
+        })
+    }
+}
+
+impl Display for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{SECRET_KEY_PREFIX}{}", hex::encode(self.key.as_bytes()))
+    }
+}
+
+// Key files may hold comments, only the first line with the expected prefix is used.
+// Shared with the signing keys, which use the same file format
+pub fn parse_key<const N: usize>(s: &str, prefix: &str) -> crate::Result<[u8; N]> {
+    let line = s
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with(prefix))
+        .ok_or(format!("No key starting with {prefix} found"))?;
+    let bytes = hex::decode(&line[prefix.len()..])?;
+    let key: [u8; N] = bytes
+        .try_into()
+        .map_err(|_| format!("Key must be {N} bytes long"))?;
+    Ok(key)
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> crate::Result<Vec<u8>> {
+    encrypt_with_params(plaintext, passphrase, KdfParams::default())
+}
+
+pub fn encrypt_with_params(
+    plaintext: &[u8],
+    passphrase: &str,
+    kdf_params: KdfParams,
+) -> crate::Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = kdf_params.derive_key(passphrase, &salt)?;
+
+    let mut header: Vec<u8> = MAGIC.to_vec();
+    header.push(VERSION);
+    header.push(MODE_PASSPHRASE);
+    header.extend(kdf_params.as_bytes());
+    header.extend(salt);
+    seal(&key, header, plaintext)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> crate::Result<Vec<u8>> {
+    let mode = check_header(data)?;
+    if mode == MODE_RECIPIENTS {
+        return Err("Message is encrypted to public keys, a private key is required".into());
+    }
+    if mode != MODE_PASSPHRASE {
+        return Err(format!("Unsupported encryption mode: {mode}").into());
+    }
+
+    let header_len = MAGIC.len() + 2 + KDF_PARAMS_LEN + SALT_LEN;
+    if data.len() < header_len {
+        return Err("Encrypted payload is truncated".into());
+    }
+    let kdf_params = KdfParams::from_bytes(&data[MAGIC.len() + 2..])?;
+    let salt = &data[header_len - SALT_LEN..header_len];
+    let key = kdf_params.derive_key(passphrase, salt)?;
+    open(&key, data, header_len)
+}
+
+// A fresh content key is wrapped once per recipient with a key derived from X25519
+pub fn encrypt_to_recipients(plaintext: &[u8], recipients: &[PublicKey]) -> crate::Result<Vec<u8>> {
+    if recipients.is_empty() || recipients.len() > MAX_RECIPIENTS {
+        return Err(format!("Between 1 and {MAX_RECIPIENTS} recipients are required").into());
+    }
+    let content_key: [u8; KEY_LEN] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+    // A static secret is used as the ephemeral key since it takes part in several exchanges
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral);
+
+    let mut header: Vec<u8> = MAGIC.to_vec();
+    header.push(VERSION);
+    header.push(MODE_RECIPIENTS);
+    header.extend(ephemeral_public.as_bytes());
+    header.push(recipients.len() as u8);
+
+    for recipient in recipients {
+        let shared_secret = ephemeral.diffie_hellman(&recipient.key);
+        if !shared_secret.was_contributory() {
+            return Err("Invalid recipient public key".into());
+        }
+        let key = wrapping_key(
+            shared_secret.as_bytes(),
+            ephemeral_public.as_bytes(),
+            recipient.as_bytes(),
+        );
+        // Every wrapping key is unique to this message, so a fixed nonce is safe
+        let wrapped = ChaCha20Poly1305::new(&key.into())
+            .encrypt(&[0u8; NONCE_LEN].into(), content_key.as_slice())
+            .map_err(|_| "Key wrapping failed")?;
+        header.extend(wrapped);
+    }
+    seal(&content_key, header, plaintext)
+}
+
+pub fn decrypt_with_key(data: &[u8], secret_key: &SecretKey) -> crate::Result<Vec<u8>> {
+    let mode = check_header(data)?;
+    if mode == MODE_PASSPHRASE {
+        return Err("Message is encrypted with a passphrase, not to public keys".into());
+    }
+    if mode != MODE_RECIPIENTS {
+        return Err(format!("Unsupported encryption mode: {mode}").into());
+    }
+
+    let keys_start = MAGIC.len() + 2 + KEY_LEN + 1;
+    if data.len() < keys_start {
+        return Err("Encrypted payload is truncated".into());
+    }
+    let ephemeral_public: [u8; KEY_LEN] = data[MAGIC.len() + 2..keys_start - 1].try_into()?;
+    let recipient_count = data[keys_start - 1] as usize;
+    let header_len = keys_start + recipient_count * WRAPPED_KEY_LEN;
+    if data.len() < header_len {
+        return Err("Encrypted payload is truncated".into());
+    }
+
+    let public_key = secret_key.public_key();
+    let shared_secret = secret_key.key.diffie_hellman(&ephemeral_public.into());
+    let key = wrapping_key(
+        shared_secret.as_bytes(),
+        &ephemeral_public,
+        public_key.as_bytes(),
+    );
+    let cipher = ChaCha20Poly1305::new(&key.into());
+    let content_key = data[keys_start..header_len]
+        .chunks_exact(WRAPPED_KEY_LEN)
+        .find_map(|wrapped| cipher.decrypt(&[0u8; NONCE_LEN].into(), wrapped).ok())
+        .ok_or("Message was not encrypted to this key")?;
+    open(content_key.as_slice().try_into()?, data, header_len)
+}
+
+fn wrapping_key(shared_secret: &[u8], ephemeral_public: &[u8], recipient: &[u8]) -> [u8; KEY_LEN] {
+    let salt: Vec<u8> = ephemeral_public.iter().chain(recipient).copied().collect();
+    let mut key = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(b"pngme recipient key", &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+fn check_header(data: &[u8]) -> crate::Result<u8> {
+    if !is_encrypted(data) || data.len() < MAGIC.len() + 2 {
+        return Err("Payload is not encrypted".into());
+    }
+    let version = data[MAGIC.len()];
+    if version != VERSION {
+        return Err(format!("Unsupported encryption version: {version}").into());
+    }
+    Ok(data[MAGIC.len() + 1])
+}
+
+// The header is passed as associated data so tampering with it fails authentication too
+fn seal(key: &[u8; KEY_LEN], header: Vec<u8>, plaintext: &[u8]) -> crate::Result<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut aad = header;
+    aad.extend(nonce);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "Encryption failed")?;
+    aad.extend(ciphertext);
+    Ok(aad)
+}
+
+fn open(key: &[u8; KEY_LEN], data: &[u8], header_len: usize) -> crate::Result<Vec<u8>> {
+    if data.len() < header_len + NONCE_LEN {
+        return Err("Encrypted payload is truncated".into());
+    }
+    let (aad, ciphertext) = data.split_at(header_len + NONCE_LEN);
+    let nonce = &aad[header_len..];
+    let cipher = ChaCha20Poly1305::new(key.into());
+    cipher
+        .decrypt(
+            nonce.into(),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| "Decryption failed: wrong passphrase or the message was tampered with".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_params() -> KdfParams {
+        KdfParams::new(64, 1, 1)
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let message = b"This is where your secret message will be!";
+        let data = encrypt_with_params(message, "hunter2", testing_params()).unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(message.len()).any(|window| window == message));
+        assert_eq!(decrypt(&data, "hunter2").unwrap(), message);
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let data = encrypt_with_params(b"secret", "hunter2", testing_params()).unwrap();
+        assert!(decrypt(&data, "hunter3").is_err());
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let data = encrypt_with_params(b"secret", "hunter2", testing_params()).unwrap();
+        for idx in [MAGIC.len() + 5, data.len() - 1, data.len() - 20] {
+            let mut tampered = data.clone();
+            tampered[idx] ^= 1;
+            assert!(decrypt(&tampered, "hunter2").is_err());
+        }
+        assert!(decrypt(&data[..data.len() - 1], "hunter2").is_err());
+        assert!(decrypt(&data[..10], "hunter2").is_err());
+    }
+
+    #[test]
+    fn test_excessive_kdf_params_are_refused() {
+        let mut data = encrypt_with_params(b"secret", "hunter2", testing_params()).unwrap();
+        data[MAGIC.len() + 2] = 0xff;
+        assert!(decrypt(&data, "hunter2").is_err());
+    }
+
+    #[test]
+    fn test_kdf_header_over_limits_is_rejected_before_derivation() {
+        let data = encrypt_with_params(b"secret", "hunter2", testing_params()).unwrap();
+        let params_offset = MAGIC.len() + 2;
+        let over_limits = [
+            KdfParams::new(MAX_MEMORY_KIB + 1, 1, 1),
+            KdfParams::new(64, MAX_ITERATIONS + 1, 1),
+            KdfParams::new(64, 1, MAX_PARALLELISM + 1),
+        ];
+        for params in over_limits {
+            let mut crafted = data.clone();
+            crafted[params_offset..params_offset + KDF_PARAMS_LEN]
+                .copy_from_slice(&params.as_bytes());
+            assert!(KdfParams::from_bytes(&params.as_bytes()).is_err());
+            let error = decrypt(&crafted, "hunter2").unwrap_err().to_string();
+            assert!(error.contains("exceed the allowed limits"), "{error}");
+        }
+        let at_limits = KdfParams::new(MAX_MEMORY_KIB, MAX_ITERATIONS, MAX_PARALLELISM);
+        assert!(KdfParams::from_bytes(&at_limits.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_encrypt_to_recipients() {
+        let alice = SecretKey::generate();
+        let bob = SecretKey::generate();
+        let eve = SecretKey::generate();
+        let data =
+            encrypt_to_recipients(b"secret", &[alice.public_key(), bob.public_key()]).unwrap();
+
+        assert_eq!(decrypt_with_key(&data, &alice).unwrap(), b"secret");
+        assert_eq!(decrypt_with_key(&data, &bob).unwrap(), b"secret");
+        assert!(decrypt_with_key(&data, &eve).is_err());
+        assert!(decrypt(&data, "hunter2").is_err());
+
+        let mut tampered = data.clone();
+        tampered[MAGIC.len() + 3] ^= 1;
+        assert!(decrypt_with_key(&tampered, &alice).is_err());
+    }
+
+    #[test]
+    fn test_key_text_round_trip() {
+        let secret_key = SecretKey::generate();
+        let text = format!("# comment\n{secret_key}\n");
+        let parsed = SecretKey::from_str(&text).unwrap();
+        assert_eq!(parsed.public_key(), secret_key.public_key());
+
+        let public_key = secret_key.public_key();
+        assert!(public_key.to_string().starts_with(PUBLIC_KEY_PREFIX));
+        assert_eq!(
+            PublicKey::from_str(&public_key.to_string()).unwrap(),
+            public_key
+        );
+        assert!(PublicKey::from_str(&secret_key.to_string()).is_err());
+        assert!(PublicKey::from_str("pngme-public-key:abcd").is_err());
+    }
+
+    #[test]
+    fn test_plain_payload_is_not_encrypted() {
+        assert!(!is_encrypted(b"plain message"));
+        assert!(decrypt(b"plain message", "hunter2").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+// Runtime side of #[derive(PngChunk)], every field of a derived struct implements ChunkField
+pub trait ChunkField: Sized {
+    fn write_to(&self, data: &mut Vec<u8>);
+
+    fn read_from(reader: &mut FieldReader) -> crate::Result<Self>;
+}
+
+// Fields marked #[png_chunk(rest)] take everything left in the chunk without a length prefix
+pub trait RestField: Sized {
+    fn write_rest(&self, data: &mut Vec<u8>);
+
+    fn read_rest(reader: &mut FieldReader) -> crate::Result<Self>;
+}
+
+pub struct FieldReader<'a> {
+    chunk_type: &'static str,
+    bytes: &'a [u8],
+}
+
+impl<'a> FieldReader<'a> {
+    pub fn new(chunk_type: &'static str, bytes: &'a [u8]) -> Self {
+        Self { chunk_type, bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(format!(
+                "{} chunk is truncated. Needed {len} more bytes, found {}",
+                self.chunk_type,
+                self.bytes.len()
+            )
+            .into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+
+    pub fn finish(self) -> crate::Result<()> {
+        if !self.bytes.is_empty() {
+            return Err(format!(
+                "{} trailing bytes after the last {} field",
+                self.bytes.len(),
+                self.chunk_type
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+macro_rules! big_endian_field {
+    ($($int:ty),*) => {
+        $(
+            impl ChunkField for $int {
+                fn write_to(&self, data: &mut Vec<u8>) {
+                    data.extend(self.to_be_bytes());
+                }
+
+                fn read_from(reader: &mut FieldReader) -> crate::Result<Self> {
+                    Ok(<$int>::from_be_bytes(reader.take(size_of::<$int>())?.try_into()?))
+                }
+            }
+        )*
+    };
+}
+
+big_endian_field!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl ChunkField for bool {
+    fn write_to(&self, data: &mut Vec<u8>) {
+        data.push(*self as u8);
+    }
+
+    fn read_from(reader: &mut FieldReader) -> crate::Result<Self> {
+        match reader.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(format!("Invalid {} flag value: {other}", reader.chunk_type).into()),
+        }
+    }
+}
+
+impl<const N: usize> ChunkField for [u8; N] {
+    fn write_to(&self, data: &mut Vec<u8>) {
+        data.extend(self);
+    }
+
+    fn read_from(reader: &mut FieldReader) -> crate::Result<Self> {
+        Ok(reader.take(N)?.try_into()?)
+    }
+}
+
+// Variable length fields carry a u32 length prefix
+impl ChunkField for Vec<u8> {
+    fn write_to(&self, data: &mut Vec<u8>) {
+        (self.len() as u32).write_to(data);
+        data.extend(self);
+    }
+
+    fn read_from(reader: &mut FieldReader) -> crate::Result<Self> {
+        let len = u32::read_from(reader)? as usize;
+        Ok(reader.take(len)?.to_vec())
+    }
+}
+
+impl ChunkField for String {
+    fn write_to(&self, data: &mut Vec<u8>) {
+        (self.len() as u32).write_to(data);
+        data.extend(self.as_bytes());
+    }
+
+    fn read_from(reader: &mut FieldReader) -> crate::Result<Self> {
+        Ok(String::from_utf8(Vec::read_from(reader)?)?)
+    }
+}
+
+impl RestField for Vec<u8> {
+    fn write_rest(&self, data: &mut Vec<u8>) {
+        data.extend(self);
+    }
+
+    fn read_rest(reader: &mut FieldReader) -> crate::Result<Self> {
+        Ok(reader.rest().to_vec())
+    }
+}
+
+impl RestField for String {
+    fn write_rest(&self, data: &mut Vec<u8>) {
+        data.extend(self.as_bytes());
+    }
+
+    fn read_rest(reader: &mut FieldReader) -> crate::Result<Self> {
+        Ok(String::from_utf8(reader.rest().to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::PngChunk;
+    use std::str::FromStr;
+
+    #[derive(Debug, PartialEq, PngChunk)]
+    #[png_chunk(chunk_type = "veRs")]
+    struct Version {
+        major: u16,
+        minor: u16,
+        beta: bool,
+        build: [u8; 4],
+        name: String,
+        #[png_chunk(rest)]
+        notes: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, PngChunk)]
+    #[png_chunk(chunk_type = "ofFs")]
+    struct Offset(i32, i64);
+
+    fn testing_version() -> Version {
+        Version {
+            major: 1,
+            minor: 2,
+            beta: true,
+            build: *b"a1b2",
+            name: "Dice".to_string(),
+            notes: b"notes".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_derived_layout() {
+        let chunk = testing_version().to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "veRs");
+        assert_eq!(
+            chunk.data(),
+            b"\x00\x01\x00\x02\x01a1b2\x00\x00\x00\x04Dicenotes"
+        );
+        assert_eq!(Version::try_from(&chunk).unwrap(), testing_version());
+    }
+
+    #[test]
+    fn test_derived_tuple_struct() {
+        let offset = Offset(-2, 1 << 40);
+        let chunk = offset.to_chunk();
+        assert_eq!(Offset::CHUNK_TYPE, "ofFs");
+        assert_eq!(chunk.data().len(), 12);
+        assert_eq!(Offset::try_from(&chunk).unwrap(), offset);
+    }
+
+    #[test]
+    fn test_derived_rejects_bad_data() {
+        let chunk = testing_version().to_chunk();
+        let truncated = Chunk::new(chunk.chunk_type().clone(), chunk.data()[..6].to_vec());
+        assert!(Version::try_from(&truncated).is_err());
+
+        let mut data = Offset(1, 2).to_chunk().data().to_vec();
+        data.push(0);
+        let padded = Chunk::new(ChunkType::from_str("ofFs").unwrap(), data);
+        assert!(Offset::try_from(&padded).is_err());
+
+        assert!(Offset::try_from(&chunk).is_err());
+    }
+}
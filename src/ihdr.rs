@@ -0,0 +1,245 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    Indexed,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+impl ColorType {
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+
+    pub fn allowed_bit_depths(&self) -> &'static [u8] {
+        match self {
+            Self::Grayscale => &[1, 2, 4, 8, 16],
+            Self::Indexed => &[1, 2, 4, 8],
+            Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => &[8, 16],
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = crate::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::Rgb),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::Rgba),
+            other => Err(format!("Invalid IHDR color type: {other}").into()),
+        }
+    }
+}
+
+impl From<ColorType> for u8 {
+    fn from(value: ColorType) -> Self {
+        match value {
+            ColorType::Grayscale => 0,
+            ColorType::Rgb => 2,
+            ColorType::Indexed => 3,
+            ColorType::GrayscaleAlpha => 4,
+            ColorType::Rgba => 6,
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Grayscale => "grayscale",
+            Self::Rgb => "RGB",
+            Self::Indexed => "indexed",
+            Self::GrayscaleAlpha => "grayscale+alpha",
+            Self::Rgba => "RGBA",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ImageHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+
+impl ImageHeader {
+    pub const CHUNK_TYPE: &'static str = "IHDR";
+    pub const DATA_LEN: usize = 13;
+
+    pub fn new(
+        width: u32,
+        height: u32,
+        bit_depth: u8,
+        color_type: ColorType,
+        interlaced: bool,
+    ) -> crate::Result<Self> {
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(format!("Invalid image dimensions: {width}x{height}").into());
+        }
+        if !color_type.allowed_bit_depths().contains(&bit_depth) {
+            return Err(
+                format!("Bit depth {bit_depth} not allowed for {color_type} images").into(),
+            );
+        }
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced,
+        })
+    }
+
+    pub fn from_png(png: &Png) -> crate::Result<Self> {
+        match png.chunks().first() {
+            Some(chunk) if chunk.chunk_type().to_string() == Self::CHUNK_TYPE => {
+                Self::try_from(chunk)
+            }
+            _ => Err("IHDR must be the first chunk".into()),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    pub fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    pub fn interlaced(&self) -> bool {
+        self.interlaced
+    }
+
+    // Indexed images store 8-bit palette samples whatever their bit depth
+    pub fn sample_depth(&self) -> u8 {
+        match self.color_type {
+            ColorType::Indexed => 8,
+            _ => self.bit_depth,
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data: Vec<u8> = self
+            .width
+            .to_be_bytes()
+            .iter()
+            .chain(self.height.to_be_bytes().iter())
+            .chain(
+                [
+                    self.bit_depth,
+                    self.color_type.into(),
+                    0,
+                    0,
+                    self.interlaced as u8,
+                ]
+                .iter(),
+            )
+            .copied()
+            .collect();
+        Chunk::new(ChunkType::IHDR, data)
+    }
+}
+
+impl TryFrom<&Chunk> for ImageHeader {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(Self::DATA_LEN)?;
+        let data = chunk.data();
+        if data[10] != 0 || data[11] != 0 {
+            return Err("Unknown IHDR compression or filter method".into());
+        }
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            other => return Err(format!("Invalid IHDR interlace method: {other}").into()),
+        };
+
+        Self::new(
+            u32::from_be_bytes(data[0..4].try_into()?),
+            u32::from_be_bytes(data[4..8].try_into()?),
+            data[8],
+            ColorType::try_from(data[9])?,
+            interlaced,
+        )
+    }
+}
+
+impl Display for ImageHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {}{}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.color_type,
+            if self.interlaced { ", interlaced" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_ihdr_round_trip() {
+        let header = ImageHeader::new(50, 50, 8, ColorType::Rgba, false).unwrap();
+        let chunk = header.to_chunk();
+        assert_eq!(chunk.data(), &[0, 0, 0, 50, 0, 0, 0, 50, 8, 6, 0, 0, 0]);
+        assert_eq!(ImageHeader::try_from(&chunk).unwrap(), header);
+    }
+
+    #[test]
+    fn test_invalid_bit_depth() {
+        assert!(ImageHeader::new(1, 1, 16, ColorType::Indexed, false).is_err());
+        assert!(ImageHeader::new(1, 1, 4, ColorType::Rgb, false).is_err());
+        assert!(ImageHeader::new(1, 1, 4, ColorType::Grayscale, false).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_ihdr_chunk() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("IHDR").unwrap(),
+            vec![0, 0, 0, 1, 0, 0, 0, 1, 8, 5, 0, 0, 0],
+        );
+        assert!(ImageHeader::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_sample_depth() {
+        let header = ImageHeader::new(1, 1, 2, ColorType::Indexed, false).unwrap();
+        assert_eq!(header.sample_depth(), 8);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::{self, ChunkType};
+use crate::png::Png;
+use crate::registry;
+use std::fmt::Write;
+
+const PREVIEW_LEN: usize = 16;
+
+const RESET: &str = "\x1b[0m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const DIM: &str = "\x1b[2m";
+
+// Read straight from the bytes so corrupted chunks still show up with their CRC status
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChunkEntry {
+    index: usize,
+    offset: usize,
+    type_bytes: [u8; 4],
+    data: Vec<u8>,
+    crc: u32,
+    expected_crc: u32,
+}
+
+impl ChunkEntry {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn length(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn chunk_type(&self) -> Option<ChunkType> {
+        ChunkType::checked(self.type_bytes)
+    }
+
+    pub fn type_name(&self) -> String {
+        chunk_type::escape(&self.type_bytes)
+    }
+
+    pub fn crc_valid(&self) -> bool {
+        self.crc == self.expected_crc
+    }
+
+    pub fn known_name(&self) -> Option<&'static str> {
+        registry::lookup(&self.type_bytes).map(|info| info.name)
+    }
+
+    pub fn preview(&self) -> String {
+        let shown = &self.data[..self.data.len().min(PREVIEW_LEN)];
+        let ellipsis = if self.data.len() > PREVIEW_LEN {
+            "…"
+        } else {
+            ""
+        };
+        if !shown.is_empty() && shown.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            format!("\"{}\"{ellipsis}", String::from_utf8_lossy(shown))
+        } else {
+            format!("{}{ellipsis}", hex::encode(shown))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Inspection {
+    entries: Vec<ChunkEntry>,
+    trailing: usize,
+    issues: Vec<String>,
+}
+
+impl Inspection {
+    pub fn scan(bytes: &[u8]) -> crate::Result<Self> {
+        if !bytes.starts_with(&Png::STANDARD_HEADER) {
+            return Err("First 8 bytes doesn't correpond to the PNG spec".into());
+        }
+        let mut entries = Vec::new();
+        let mut issues = Vec::new();
+        let mut offset = Png::STD_HEADER_LENGTH;
+        let overhead =
+            Chunk::DATA_LEN_BYTES_LEN + Chunk::CHUNK_TYPE_BYTES_LEN + Chunk::CRC_BYTES_LEN;
+
+        while bytes.len() - offset >= overhead {
+            let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into()?) as usize;
+            if length > bytes.len() - offset - overhead {
+                issues.push(format!(
+                    "Chunk {} at offset {offset} declares {length} bytes, more than the file holds",
+                    entries.len()
+                ));
+                break;
+            }
+            let type_bytes: [u8; 4] = bytes[offset + 4..offset + 8].try_into()?;
+            let data = bytes[offset + 8..offset + 8 + length].to_vec();
+            let crc_start = offset + 8 + length;
+            let crc = u32::from_be_bytes(bytes[crc_start..crc_start + 4].try_into()?);
+            let expected_crc =
+                crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(&bytes[offset + 4..crc_start]);
+            entries.push(ChunkEntry {
+                index: entries.len(),
+                offset,
+                type_bytes,
+                data,
+                crc,
+                expected_crc,
+            });
+            offset = crc_start + 4;
+            if type_bytes == *b"IEND" {
+                break;
+            }
+        }
+        let trailing = bytes.len() - offset;
+        if trailing > 0 {
+            issues.push(format!("{trailing} bytes after the last chunk"));
+        }
+        Ok(Self {
+            entries,
+            trailing,
+            issues,
+        })
+    }
+
+    pub fn entries(&self) -> &[ChunkEntry] {
+        &self.entries
+    }
+
+    pub fn trailing(&self) -> usize {
+        self.trailing
+    }
+
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: String| {
+            if color {
+                format!("{code}{text}{RESET}")
+            } else {
+                text
+            }
+        };
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{:>3}  {:>8}  {:>8}  {:<4}  {:<9}  {:<7}  {:<8}  {:<6}  {:<4}  {:<30}  Preview",
+            "#", "Offset", "Length", "Type", "Kind", "Scope", "Reserved", "Copy", "CRC", "Name"
+        )
+        .unwrap();
+        for entry in &self.entries {
+            // Flag columns come from the case bits of each type byte
+            let flags = match entry.chunk_type() {
+                Some(chunk_type) => [
+                    if chunk_type.is_critical() {
+                        paint(YELLOW, format!("{:<9}", "critical"))
+                    } else {
+                        format!("{:<9}", "ancillary")
+                    },
+                    format!(
+                        "{:<7}",
+                        if chunk_type.is_public() {
+                            "public"
+                        } else {
+                            "private"
+                        }
+                    ),
+                    if chunk_type.is_reserved_bit_valid() {
+                        format!("{:<8}", "ok")
+                    } else {
+                        paint(RED, format!("{:<8}", "set"))
+                    },
+                    format!(
+                        "{:<6}",
+                        if chunk_type.is_safe_to_copy() {
+                            "safe"
+                        } else {
+                            "unsafe"
+                        }
+                    ),
+                ],
+                None => [
+                    paint(RED, format!("{:<9}", "invalid")),
+                    format!("{:<7}", "?"),
+                    format!("{:<8}", "?"),
+                    format!("{:<6}", "?"),
+                ],
+            };
+            let crc = if entry.crc_valid() {
+                paint(GREEN, format!("{:<4}", "ok"))
+            } else {
+                paint(RED, format!("{:<4}", "BAD"))
+            };
+            let name = match entry.known_name() {
+                Some(name) => format!("{name:<30}"),
+                None => paint(DIM, format!("{:<30}", "unknown")),
+            };
+            writeln!(
+                output,
+                "{:>3}  {:>8}  {:>8}  {}  {}  {}  {}  {}  {crc}  {name}  {}",
+                entry.index,
+                entry.offset,
+                entry.length(),
+                paint(CYAN, format!("{:<4}", entry.type_name())),
+                flags[0],
+                flags[1],
+                flags[2],
+                flags[3],
+                entry.preview()
+            )
+            .unwrap();
+        }
+        for issue in &self.issues {
+            writeln!(output, "{}", paint(RED, format!("Warning: {issue}"))).unwrap();
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                b"This is where your secret message will be!".to_vec(),
+            ),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_scan_offsets_and_flags() {
+        let inspection = Inspection::scan(&testing_bytes()).unwrap();
+        let entries = inspection.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].offset(), 8 + 12 + 13);
+        assert_eq!(entries[0].known_name(), Some("Image header"));
+        assert!(entries.iter().all(ChunkEntry::crc_valid));
+        let chunk_type = entries[1].chunk_type().unwrap();
+        assert!(!chunk_type.is_critical() && chunk_type.is_safe_to_copy());
+        assert_eq!(entries[1].preview(), "\"This is where yo\"…");
+        assert!(inspection.issues().is_empty());
+    }
+
+    #[test]
+    fn test_scan_bad_crc_and_trailing_data() {
+        let mut bytes = testing_bytes();
+        bytes[8 + 12 + 13 + 8] ^= 0xff;
+        bytes.extend(b"junk");
+        let inspection = Inspection::scan(&bytes).unwrap();
+        assert!(!inspection.entries()[1].crc_valid());
+        assert_eq!(inspection.trailing(), 4);
+    }
+
+    #[test]
+    fn test_render_without_color() {
+        let rendered = Inspection::scan(&testing_bytes()).unwrap().render(false);
+        assert!(!rendered.contains('\x1b'));
+        assert_eq!(rendered.lines().count(), 4);
+    }
+}
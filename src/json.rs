@@ -0,0 +1,344 @@
+use crate::apng::{AnimationControl, FrameControl};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::codec;
+use crate::color::{Chromaticities, Cicp, Gamma, RenderingIntent};
+use crate::ihdr::ImageHeader;
+use crate::palette::{
+    Background, Histogram, Palette, SignificantBits, SuggestedPalette, Transparency,
+};
+use crate::payload::Payload;
+use crate::phys::{PhysicalDimensions, Unit};
+use crate::png::Png;
+use crate::signature::Signature;
+use base64::Engine;
+use serde_json::{json, Value};
+
+// Bumped whenever a field is renamed or removed, additions keep the version
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub fn png(png: &Png) -> Value {
+    let header = ImageHeader::from_png(png).ok();
+    let mut offset = Png::STD_HEADER_LENGTH;
+    let mut chunks = Vec::with_capacity(png.chunks().len());
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        chunks.push(self::chunk(chunk, index, offset, header.as_ref()));
+        offset += chunk.as_bytes().len();
+    }
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "size": offset + png.trailing().len(),
+        "chunks": chunks,
+    })
+}
+
+pub fn chunk(chunk: &Chunk, index: usize, offset: usize, header: Option<&ImageHeader>) -> Value {
+    let chunk_type = chunk.chunk_type();
+    let (fields, error) = or_error(fields(chunk, header));
+    json!({
+        "index": index,
+        "type": chunk_type.to_string(),
+        "length": chunk.length(),
+        "offset": offset,
+        "crc": chunk.crc(),
+        "flags": {
+            "critical": chunk_type.is_critical(),
+            "public": chunk_type.is_public(),
+            "reserved_bit_valid": chunk_type.is_reserved_bit_valid(),
+            "safe_to_copy": chunk_type.is_safe_to_copy(),
+        },
+        "fields": fields,
+        "error": error,
+    })
+}
+
+// A decoded message or file, the bytes are always given as base64 as well
+pub fn payload(chunk_type: &str, data: Option<&[u8]>) -> crate::Result<Value> {
+    let data = match data {
+        None => {
+            return Ok(json!({
+                "schema_version": SCHEMA_VERSION,
+                "chunk_type": chunk_type,
+                "found": false,
+            }))
+        }
+        Some(data) => data,
+    };
+    let (kind, file, data) = match Payload::from_bytes(data.to_vec())? {
+        Payload::File(attachment) => {
+            let file = json!({
+                "name": attachment.filename(),
+                "size": attachment.size(),
+                "mime_type": attachment.mime_type(),
+                "sha256": hex::encode(attachment.hash()),
+            });
+            ("file", file, attachment.data().to_vec())
+        }
+        Payload::Message(data) => ("message", Value::Null, data),
+    };
+    let (decoded, error) = or_error(codec::to_json(chunk_type, &data).unwrap_or(Ok(Value::Null)));
+    Ok(json!({
+        "schema_version": SCHEMA_VERSION,
+        "chunk_type": chunk_type,
+        "found": true,
+        "kind": kind,
+        "length": data.len(),
+        "text": std::str::from_utf8(&data).ok(),
+        "base64": base64::engine::general_purpose::STANDARD.encode(&data),
+        "file": file,
+        "decoded": decoded,
+        "error": error,
+    }))
+}
+
+// Decoded contents of the chunks we know, null for unknown ones
+pub fn fields(chunk: &Chunk, header: Option<&ImageHeader>) -> crate::Result<Value> {
+    codec::with_registry(|registry| registry.chunk_to_json(chunk, header))
+        .unwrap_or(Ok(Value::Null))
+}
+
+// A value that fails to decode is null, with the reason next to it
+fn or_error(value: crate::Result<Value>) -> (Value, Option<String>) {
+    match value {
+        Ok(value) => (value, None),
+        Err(e) => (Value::Null, Some(e.to_string())),
+    }
+}
+
+// Registered as codecs, so a user codec for one of these types replaces it
+pub const BUILTIN_FIELDS: [(ChunkType, codec::BuiltinJson); 15] = [
+    (ChunkType::IHDR, image_header),
+    (ChunkType::PLTE, palette),
+    (ChunkType::TRNS, transparency),
+    (ChunkType::BKGD, background),
+    (ChunkType::HIST, histogram),
+    (ChunkType::SBIT, significant_bits),
+    (ChunkType::SPLT, suggested_palette),
+    (ChunkType::GAMA, gamma),
+    (ChunkType::CHRM, chromaticities),
+    (ChunkType::SRGB, rendering_intent),
+    (ChunkType::CICP, cicp),
+    (ChunkType::PHYS, physical_dimensions),
+    (ChunkType::ACTL, animation_control),
+    (ChunkType::FCTL, frame_control),
+    (ChunkType::SIGN, signature),
+];
+
+fn needs_header(header: Option<&ImageHeader>) -> crate::Result<&ImageHeader> {
+    Ok(header.ok_or("decoding needs a valid IHDR")?)
+}
+
+fn image_header(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let header = ImageHeader::try_from(chunk)?;
+    Ok(json!({
+        "width": header.width(),
+        "height": header.height(),
+        "bit_depth": header.bit_depth(),
+        "color_type": u8::from(header.color_type()),
+        "color_type_name": header.color_type().to_string(),
+        "interlaced": header.interlaced(),
+    }))
+}
+
+fn palette(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let palette = Palette::try_from(chunk)?;
+    Ok(json!({ "entries": palette.entries() }))
+}
+
+fn transparency(chunk: &Chunk, header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let header = needs_header(header)?;
+    Ok(match Transparency::from_chunk(chunk, header)? {
+        Transparency::Gray(gray) => json!({ "gray": gray }),
+        Transparency::Rgb(rgb) => json!({ "rgb": rgb }),
+        Transparency::Alpha(alpha) => json!({ "alpha": alpha }),
+    })
+}
+
+fn background(chunk: &Chunk, header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let header = needs_header(header)?;
+    Ok(match Background::from_chunk(chunk, header)? {
+        Background::Gray(gray) => json!({ "gray": gray }),
+        Background::Rgb(rgb) => json!({ "rgb": rgb }),
+        Background::Index(index) => json!({ "index": index }),
+    })
+}
+
+fn histogram(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    Ok(json!({ "frequencies": Histogram::try_from(chunk)?.frequencies() }))
+}
+
+fn significant_bits(chunk: &Chunk, header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let bits = SignificantBits::from_chunk(chunk, needs_header(header)?)?;
+    Ok(json!({ "bits": bits.bits() }))
+}
+
+fn suggested_palette(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let palette = SuggestedPalette::try_from(chunk)?;
+    let entries: Vec<Value> = palette
+        .entries()
+        .iter()
+        .map(|entry| {
+            json!([
+                entry.red,
+                entry.green,
+                entry.blue,
+                entry.alpha,
+                entry.frequency
+            ])
+        })
+        .collect();
+    Ok(json!({
+        "name": palette.name(),
+        "sample_depth": palette.sample_depth(),
+        "entries": entries,
+    }))
+}
+
+fn gamma(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let gamma = Gamma::try_from(chunk)?;
+    Ok(json!({ "value": gamma.value(), "gamma": gamma.gamma() }))
+}
+
+fn chromaticities(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let chromaticities = Chromaticities::try_from(chunk)?;
+    Ok(json!({
+        "white_point": chromaticities.white_point(),
+        "red": chromaticities.red(),
+        "green": chromaticities.green(),
+        "blue": chromaticities.blue(),
+    }))
+}
+
+fn rendering_intent(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let intent = RenderingIntent::try_from(chunk)?;
+    Ok(json!({ "rendering_intent": intent as u8, "name": intent.to_string() }))
+}
+
+fn cicp(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let cicp = Cicp::try_from(chunk)?;
+    let [primaries, transfer, matrix, full_range] = cicp.values();
+    Ok(json!({
+        "colour_primaries": primaries,
+        "transfer_function": transfer,
+        "matrix_coefficients": matrix,
+        "full_range": full_range == 1,
+    }))
+}
+
+fn physical_dimensions(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let phys = PhysicalDimensions::try_from(chunk)?;
+    Ok(json!({
+        "x": phys.x(),
+        "y": phys.y(),
+        "unit": match phys.unit() {
+            Unit::Unknown => "unknown",
+            Unit::Meter => "meter",
+        },
+    }))
+}
+
+fn animation_control(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let control = AnimationControl::try_from(chunk)?;
+    Ok(json!({
+        "num_frames": control.num_frames(),
+        "num_plays": control.num_plays(),
+    }))
+}
+
+fn frame_control(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let control = FrameControl::try_from(chunk)?;
+    Ok(json!({
+        "sequence_number": control.sequence_number,
+        "width": control.width,
+        "height": control.height,
+        "x_offset": control.x_offset,
+        "y_offset": control.y_offset,
+        "delay_num": control.delay_num,
+        "delay_den": control.delay_den,
+        "dispose_op": control.dispose_op as u8,
+        "blend_op": control.blend_op as u8,
+    }))
+}
+
+fn signature(chunk: &Chunk, _header: Option<&ImageHeader>) -> crate::Result<Value> {
+    let signature = Signature::try_from(chunk)?;
+    Ok(json!({
+        "target": signature.target().to_string(),
+        "covers_image": signature.covers_image(),
+        "key": signature.key().to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachment::Attachment;
+    use crate::ihdr::ColorType;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        let header = ImageHeader::new(50, 40, 8, ColorType::Rgb, false).unwrap();
+        Png::from_chunks(vec![
+            header.to_chunk(),
+            PhysicalDimensions::from_dpi(72.0).unwrap().to_chunk(),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![1, 2, 3]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hello".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_png_json_schema() {
+        let value = png(&testing_png());
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        let chunks = value["chunks"].as_array().unwrap();
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks[0]["type"], "IHDR");
+        assert_eq!(chunks[0]["offset"], 8);
+        assert_eq!(chunks[1]["offset"], 8 + 12 + 13);
+        assert_eq!(chunks[0]["fields"]["width"], 50);
+        assert_eq!(chunks[1]["fields"]["unit"], "meter");
+        assert_eq!(chunks[3]["flags"]["critical"], false);
+        assert_eq!(chunks[3]["fields"], Value::Null);
+        assert_eq!(value["size"], testing_png().as_bytes().len());
+    }
+
+    #[test]
+    fn test_size_includes_trailing_data() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend(b"after IEND");
+        let value = png(&Png::try_from(bytes.as_slice()).unwrap());
+        assert_eq!(value["size"], bytes.len());
+    }
+
+    #[test]
+    fn test_payload_json() {
+        let value = payload("ruSt", Some(b"hello")).unwrap();
+        assert_eq!(value["kind"], "message");
+        assert_eq!(value["text"], "hello");
+        assert_eq!(value["base64"], "aGVsbG8=");
+        let attachment = Attachment::new("a.bin", "application/octet-stream", vec![0xff]).unwrap();
+        let value = payload("ruSt", Some(&attachment.as_bytes())).unwrap();
+        assert_eq!(value["kind"], "file");
+        assert_eq!(value["text"], Value::Null);
+        assert_eq!(value["file"]["name"], "a.bin");
+        assert_eq!(payload("ruSt", None).unwrap()["found"], false);
+        let value = payload("gAMA", Some(&[1])).unwrap();
+        assert_eq!(value["decoded"], Value::Null);
+        assert!(value["error"].is_string());
+    }
+
+    #[test]
+    fn test_malformed_known_chunk_has_no_fields() {
+        let value = chunk(&Chunk::new(ChunkType::GAMA, vec![1]), 0, 8, None);
+        assert_eq!(value["fields"], Value::Null);
+        assert!(value["error"].as_str().unwrap().contains("gAMA"));
+        let value = chunk(
+            &Chunk::new(ChunkType::GAMA, vec![0, 0, 177, 143]),
+            0,
+            8,
+            None,
+        );
+        assert_eq!(value["error"], Value::Null);
+    }
+}
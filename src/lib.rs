@@ -0,0 +1,33 @@
+pub mod apng;
+mod args;
+pub mod attachment;
+pub mod carve;
+pub mod chunk;
+pub mod chunk_type;
+pub mod codec;
+pub mod color;
+pub mod commands;
+pub mod compression;
+pub mod crypto;
+pub mod field;
+pub mod ihdr;
+pub mod inspect;
+pub mod json;
+pub mod output;
+pub mod palette;
+pub mod payload;
+pub mod phys;
+pub mod png;
+pub mod registry;
+pub mod repair;
+pub mod signature;
+pub mod split;
+pub mod trailing;
+pub mod validate;
+
+// Lets the derive refer to ::pngme paths from inside this crate too
+extern crate self as pngme;
+pub use pngme_derive::PngChunk;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,12 +1,3 @@
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
-
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
-
-fn main() -> Result<()> {
-    Ok(crate::commands::Cli::run()?)
+fn main() -> pngme::Result<()> {
+    pngme::commands::Cli::run()
 }
@@ -0,0 +1,98 @@
+use base64::Engine;
+use std::fmt::Write;
+
+const HEXDUMP_WIDTH: usize = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum OutputMode {
+    // "Message: ..." for people, the only mode that isn't byte exact
+    Text,
+    Raw,
+    Hex,
+    Base64,
+    Hexdump,
+}
+
+impl OutputMode {
+    pub fn render(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Text => match std::str::from_utf8(data) {
+                Ok(msg) => format!("Message: {msg}\n").into_bytes(),
+                Err(_) => format!("Message: {data:?}\n").into_bytes(),
+            },
+            Self::Raw => data.to_vec(),
+            Self::Hex => format!("{}\n", hex::encode(data)).into_bytes(),
+            Self::Base64 => format!(
+                "{}\n",
+                base64::engine::general_purpose::STANDARD.encode(data)
+            )
+            .into_bytes(),
+            Self::Hexdump => hexdump(data).into_bytes(),
+        }
+    }
+}
+
+// Same layout as `hexdump -C`: offset, two groups of eight bytes, then printable ASCII
+pub fn hexdump(data: &[u8]) -> String {
+    let mut output = String::new();
+    for (row, line) in data.chunks(HEXDUMP_WIDTH).enumerate() {
+        write!(output, "{:08x} ", row * HEXDUMP_WIDTH).unwrap();
+        for idx in 0..HEXDUMP_WIDTH {
+            if idx % 8 == 0 {
+                output.push(' ');
+            }
+            match line.get(idx) {
+                Some(byte) => write!(output, "{byte:02x} ").unwrap(),
+                None => output.push_str("   "),
+            }
+        }
+        let ascii: String = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(output, " |{ascii}|").unwrap();
+    }
+    writeln!(output, "{:08x}", data.len()).unwrap();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"This is where your secret\n");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines[0],
+            "00000000  54 68 69 73 20 69 73 20  77 68 65 72 65 20 79 6f  |This is where yo|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  75 72 20 73 65 63 72 65  74 0a                    |ur secret.|"
+        );
+        assert_eq!(lines[2], "0000001a");
+    }
+
+    #[test]
+    fn test_render_modes() {
+        let data = [0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(OutputMode::Raw.render(&data), data);
+        assert_eq!(OutputMode::Hex.render(&data), b"deadbeef\n");
+        assert_eq!(OutputMode::Base64.render(&data), b"3q2+7w==\n");
+        assert_eq!(OutputMode::Text.render(b"hi"), b"Message: hi\n");
+    }
+}
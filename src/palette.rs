@@ -0,0 +1,707 @@
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::ihdr::{ColorType, ImageHeader};
+use crate::png::Png;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+fn check_sample(value: u16, bit_depth: u8) -> crate::Result<u16> {
+    if bit_depth < 16 && value >= 1 << bit_depth {
+        return Err(format!("Sample value {value} exceeds the {bit_depth}-bit range").into());
+    }
+    Ok(value)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Palette {
+    entries: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub const CHUNK_TYPE: &'static str = "PLTE";
+    pub const MAX_ENTRIES: usize = 256;
+
+    pub fn new(entries: Vec<[u8; 3]>) -> crate::Result<Self> {
+        if entries.is_empty() || entries.len() > Self::MAX_ENTRIES {
+            return Err(format!(
+                "PLTE must have between 1 and 256 entries, found {}",
+                entries.len()
+            )
+            .into());
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn entries(&self) -> &[[u8; 3]] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::PLTE, self.entries.concat())
+    }
+}
+
+impl TryFrom<&Chunk> for Palette {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        if !chunk.data().len().is_multiple_of(3) {
+            return Err(format!(
+                "PLTE length must be divisible by 3, found {}",
+                chunk.data().len()
+            )
+            .into());
+        }
+        Self::new(
+            chunk
+                .data()
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Transparency {
+    Gray(u16),
+    Rgb([u16; 3]),
+    Alpha(Vec<u8>),
+}
+
+impl Transparency {
+    pub const CHUNK_TYPE: &'static str = "tRNS";
+
+    pub fn from_chunk(chunk: &Chunk, header: &ImageHeader) -> crate::Result<Self> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        let data = chunk.data();
+        match header.color_type() {
+            ColorType::Grayscale => {
+                chunk.expect_len(2)?;
+                Ok(Self::Gray(check_sample(
+                    chunk::read_u16(data, 0),
+                    header.bit_depth(),
+                )?))
+            }
+            ColorType::Rgb => {
+                chunk.expect_len(6)?;
+                let mut rgb = [0u16; 3];
+                for (idx, sample) in rgb.iter_mut().enumerate() {
+                    *sample = check_sample(chunk::read_u16(data, idx * 2), header.bit_depth())?;
+                }
+                Ok(Self::Rgb(rgb))
+            }
+            ColorType::Indexed => {
+                if data.len() > Palette::MAX_ENTRIES {
+                    return Err(
+                        format!("tRNS has {} entries, at most 256 allowed", data.len()).into(),
+                    );
+                }
+                Ok(Self::Alpha(data.to_vec()))
+            }
+            color_type => {
+                Err(format!("tRNS is not allowed for {color_type} images, they carry alpha").into())
+            }
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Self::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Self::Rgb(rgb) => rgb.iter().flat_map(|s| s.to_be_bytes()).collect(),
+            Self::Alpha(alpha) => alpha.clone(),
+        };
+        Chunk::new(ChunkType::TRNS, data)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Background {
+    Gray(u16),
+    Rgb([u16; 3]),
+    Index(u8),
+}
+
+impl Background {
+    pub const CHUNK_TYPE: &'static str = "bKGD";
+
+    pub fn from_chunk(chunk: &Chunk, header: &ImageHeader) -> crate::Result<Self> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        let data = chunk.data();
+        match header.color_type() {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                chunk.expect_len(2)?;
+                Ok(Self::Gray(check_sample(
+                    chunk::read_u16(data, 0),
+                    header.bit_depth(),
+                )?))
+            }
+            ColorType::Rgb | ColorType::Rgba => {
+                chunk.expect_len(6)?;
+                let mut rgb = [0u16; 3];
+                for (idx, sample) in rgb.iter_mut().enumerate() {
+                    *sample = check_sample(chunk::read_u16(data, idx * 2), header.bit_depth())?;
+                }
+                Ok(Self::Rgb(rgb))
+            }
+            ColorType::Indexed => {
+                chunk.expect_len(1)?;
+                Ok(Self::Index(data[0]))
+            }
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Self::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Self::Rgb(rgb) => rgb.iter().flat_map(|s| s.to_be_bytes()).collect(),
+            Self::Index(idx) => vec![*idx],
+        };
+        Chunk::new(ChunkType::BKGD, data)
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gray(gray) => write!(f, "gray {gray}"),
+            Self::Rgb([r, g, b]) => write!(f, "rgb({r}, {g}, {b})"),
+            Self::Index(idx) => write!(f, "palette index {idx}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Histogram {
+    frequencies: Vec<u16>,
+}
+
+impl Histogram {
+    pub const CHUNK_TYPE: &'static str = "hIST";
+
+    pub fn new(frequencies: Vec<u16>) -> Self {
+        Self { frequencies }
+    }
+
+    pub fn frequencies(&self) -> &[u16] {
+        &self.frequencies
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(
+            ChunkType::HIST,
+            self.frequencies
+                .iter()
+                .flat_map(|f| f.to_be_bytes())
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for Histogram {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        if !chunk.data().len().is_multiple_of(2) {
+            return Err("hIST length must be even".into());
+        }
+        Ok(Self::new(
+            chunk
+                .data()
+                .chunks_exact(2)
+                .map(|f| u16::from_be_bytes([f[0], f[1]]))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignificantBits {
+    bits: Vec<u8>,
+}
+
+impl SignificantBits {
+    pub const CHUNK_TYPE: &'static str = "sBIT";
+
+    pub fn from_chunk(chunk: &Chunk, header: &ImageHeader) -> crate::Result<Self> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        // Indexed images describe the red, green and blue palette samples
+        let expected_len = match header.color_type() {
+            ColorType::Indexed => 3,
+            color_type => color_type.channels(),
+        };
+        chunk.expect_len(expected_len)?;
+        for bits in chunk.data() {
+            if *bits == 0 || *bits > header.sample_depth() {
+                return Err(
+                    format!("sBIT value {bits} outside 1..={}", header.sample_depth()).into(),
+                );
+            }
+        }
+        Ok(Self {
+            bits: chunk.data().to_vec(),
+        })
+    }
+
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SBIT, self.bits.clone())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SuggestedEntry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    pub frequency: u16,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SuggestedPalette {
+    name: String,
+    sample_depth: u8,
+    entries: Vec<SuggestedEntry>,
+}
+
+impl SuggestedPalette {
+    pub const CHUNK_TYPE: &'static str = "sPLT";
+
+    pub fn new(name: &str, sample_depth: u8, entries: Vec<SuggestedEntry>) -> crate::Result<Self> {
+        if name.is_empty() || name.len() > 79 || name.contains('\0') {
+            return Err("sPLT name must be 1-79 bytes without null characters".into());
+        }
+        if sample_depth != 8 && sample_depth != 16 {
+            return Err(format!("sPLT sample depth must be 8 or 16, found {sample_depth}").into());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            sample_depth,
+            entries,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sample_depth(&self) -> u8 {
+        self.sample_depth
+    }
+
+    pub fn entries(&self) -> &[SuggestedEntry] {
+        &self.entries
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data: Vec<u8> = self.name.bytes().collect();
+        data.push(0);
+        data.push(self.sample_depth);
+        for entry in &self.entries {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                match self.sample_depth {
+                    8 => data.push(sample as u8),
+                    _ => data.extend(sample.to_be_bytes()),
+                }
+            }
+            data.extend(entry.frequency.to_be_bytes());
+        }
+        Chunk::new(ChunkType::SPLT, data)
+    }
+}
+
+impl TryFrom<&Chunk> for SuggestedPalette {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        let data = chunk.data();
+        let name_end = data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("sPLT name is not null terminated")?;
+        // Names are Latin-1, which maps one to one onto the first 256 code points
+        let name: String = data[..name_end].iter().map(|b| *b as char).collect();
+        let sample_depth = *data.get(name_end + 1).ok_or("sPLT sample depth missing")?;
+        let entry_len = match sample_depth {
+            8 => 6,
+            16 => 10,
+            other => return Err(format!("sPLT sample depth must be 8 or 16, found {other}").into()),
+        };
+        let entries_bytes = &data[name_end + 2..];
+        if !entries_bytes.len().is_multiple_of(entry_len) {
+            return Err("sPLT entries are truncated".into());
+        }
+
+        let entries = entries_bytes
+            .chunks_exact(entry_len)
+            .map(|entry| {
+                let sample = |idx: usize| match sample_depth {
+                    8 => entry[idx] as u16,
+                    _ => chunk::read_u16(entry, idx * 2),
+                };
+                SuggestedEntry {
+                    red: sample(0),
+                    green: sample(1),
+                    blue: sample(2),
+                    alpha: sample(3),
+                    frequency: chunk::read_u16(entry, entry_len - 2),
+                }
+            })
+            .collect();
+        Self::new(&name, sample_depth, entries)
+    }
+}
+
+#[derive(Debug)]
+pub struct PaletteInfo {
+    header: ImageHeader,
+    palette: Option<Palette>,
+    transparency: Option<Transparency>,
+    background: Option<Background>,
+    histogram: Option<Histogram>,
+    significant_bits: Option<SignificantBits>,
+    suggested_palettes: Vec<SuggestedPalette>,
+    issues: Vec<String>,
+}
+
+impl PaletteInfo {
+    pub fn from_png(png: &Png) -> crate::Result<Self> {
+        let mut info = Self {
+            header: ImageHeader::from_png(png)?,
+            palette: None,
+            transparency: None,
+            background: None,
+            histogram: None,
+            significant_bits: None,
+            suggested_palettes: Vec::new(),
+            issues: Vec::new(),
+        };
+        let mut seen_palette = false;
+        let mut seen_image_data = false;
+
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type().to_string();
+            let needs_palette = [
+                Transparency::CHUNK_TYPE,
+                Background::CHUNK_TYPE,
+                Histogram::CHUNK_TYPE,
+            ]
+            .contains(&chunk_type.as_str());
+            if needs_palette && seen_image_data {
+                info.issues.push(format!(
+                    "{chunk_type} must come before the first IDAT chunk"
+                ));
+            }
+            if needs_palette && !seen_palette && info.palette_required() {
+                info.issues
+                    .push(format!("{chunk_type} must come after PLTE"));
+            }
+
+            let res = match chunk_type.as_str() {
+                "IDAT" => {
+                    seen_image_data = true;
+                    Ok(())
+                }
+                Palette::CHUNK_TYPE => {
+                    seen_palette = true;
+                    if seen_image_data {
+                        info.issues.push("PLTE must come before IDAT".into());
+                    }
+                    Palette::try_from(chunk).map(|p| info.palette = Some(p))
+                }
+                Transparency::CHUNK_TYPE => Transparency::from_chunk(chunk, &info.header)
+                    .map(|t| info.transparency = Some(t)),
+                Background::CHUNK_TYPE => {
+                    Background::from_chunk(chunk, &info.header).map(|b| info.background = Some(b))
+                }
+                Histogram::CHUNK_TYPE => {
+                    Histogram::try_from(chunk).map(|h| info.histogram = Some(h))
+                }
+                SignificantBits::CHUNK_TYPE => {
+                    if seen_palette {
+                        info.issues.push("sBIT must come before PLTE".into());
+                    }
+                    SignificantBits::from_chunk(chunk, &info.header)
+                        .map(|s| info.significant_bits = Some(s))
+                }
+                SuggestedPalette::CHUNK_TYPE => {
+                    SuggestedPalette::try_from(chunk).map(|s| info.suggested_palettes.push(s))
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = res {
+                info.issues.push(e.to_string());
+            }
+        }
+
+        info.check_against_palette();
+        Ok(info)
+    }
+
+    pub fn header(&self) -> &ImageHeader {
+        &self.header
+    }
+
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    pub fn transparency(&self) -> Option<&Transparency> {
+        self.transparency.as_ref()
+    }
+
+    pub fn background(&self) -> Option<&Background> {
+        self.background.as_ref()
+    }
+
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    pub fn significant_bits(&self) -> Option<&SignificantBits> {
+        self.significant_bits.as_ref()
+    }
+
+    pub fn suggested_palettes(&self) -> &[SuggestedPalette] {
+        &self.suggested_palettes
+    }
+
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    fn palette_required(&self) -> bool {
+        self.header.color_type() == ColorType::Indexed
+    }
+
+    fn check_against_palette(&mut self) {
+        let color_type = self.header.color_type();
+        let palette_len = match &self.palette {
+            None => {
+                if self.palette_required() {
+                    self.issues.push("Indexed image without PLTE".into());
+                }
+                if self.histogram.is_some() {
+                    self.issues.push("hIST present without PLTE".into());
+                }
+                return;
+            }
+            Some(palette) => palette.len(),
+        };
+
+        if matches!(color_type, ColorType::Grayscale | ColorType::GrayscaleAlpha) {
+            self.issues
+                .push(format!("PLTE is not allowed for {color_type} images"));
+        }
+        if self.palette_required() && palette_len > 1 << self.header.bit_depth() {
+            self.issues.push(format!(
+                "PLTE has {palette_len} entries but a {}-bit image can only index {}",
+                self.header.bit_depth(),
+                1 << self.header.bit_depth()
+            ));
+        }
+        if let Some(Transparency::Alpha(alpha)) = &self.transparency {
+            if alpha.len() > palette_len {
+                self.issues.push(format!(
+                    "tRNS has {} entries but PLTE only has {palette_len}",
+                    alpha.len()
+                ));
+            }
+        }
+        if let Some(Background::Index(idx)) = &self.background {
+            if *idx as usize >= palette_len {
+                self.issues.push(format!(
+                    "bKGD index {idx} is outside the {palette_len} entry palette"
+                ));
+            }
+        }
+        if let Some(histogram) = &self.histogram {
+            if histogram.frequencies().len() != palette_len {
+                self.issues.push(format!(
+                    "hIST has {} entries but PLTE has {palette_len}",
+                    histogram.frequencies().len()
+                ));
+            }
+        }
+    }
+}
+
+impl Display for PaletteInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(palette) = &self.palette {
+            let alpha = match &self.transparency {
+                Some(Transparency::Alpha(alpha)) => alpha.as_slice(),
+                _ => &[],
+            };
+            let frequencies = self.histogram.as_ref().map(|h| h.frequencies());
+
+            writeln!(f, "Palette: {} entries", palette.len())?;
+            writeln!(
+                f,
+                "  {:>5} {:>5} {:>5} {:>5} {:>5} {:>6}",
+                "Index", "Red", "Green", "Blue", "Alpha", "Freq"
+            )?;
+            for (idx, [r, g, b]) in palette.entries().iter().enumerate() {
+                // Palette entries past the end of tRNS are fully opaque
+                let a = alpha.get(idx).copied().unwrap_or(255);
+                let freq = frequencies
+                    .and_then(|freq| freq.get(idx))
+                    .map(|freq| freq.to_string())
+                    .unwrap_or("-".into());
+                writeln!(f, "  {idx:>5} {r:>5} {g:>5} {b:>5} {a:>5} {freq:>6}")?;
+            }
+        }
+        match &self.transparency {
+            Some(Transparency::Gray(gray)) => writeln!(f, "Transparent color: gray {gray}")?,
+            Some(Transparency::Rgb([r, g, b])) => {
+                writeln!(f, "Transparent color: rgb({r}, {g}, {b})")?
+            }
+            _ => {}
+        }
+        if let Some(background) = &self.background {
+            writeln!(f, "Background: {background}")?;
+        }
+        if let Some(significant_bits) = &self.significant_bits {
+            let bits: Vec<String> = significant_bits
+                .bits()
+                .iter()
+                .map(|b| b.to_string())
+                .collect();
+            writeln!(f, "Significant bits: {}", bits.join(", "))?;
+        }
+        for suggested in &self.suggested_palettes {
+            writeln!(
+                f,
+                "Suggested palette \"{}\": {} entries, {}-bit samples",
+                suggested.name(),
+                suggested.entries().len(),
+                suggested.sample_depth()
+            )?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "Issue: {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn to_chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+    }
+
+    fn indexed_png(chunks: Vec<Chunk>) -> Png {
+        let header = ImageHeader::new(4, 4, 2, ColorType::Indexed, false).unwrap();
+        let mut all = vec![header.to_chunk()];
+        all.extend(chunks);
+        all.push(to_chunk("IDAT", vec![0]));
+        all.push(to_chunk("IEND", vec![]));
+        Png::from_chunks(all)
+    }
+
+    fn testing_palette() -> Palette {
+        Palette::new(vec![[255, 0, 0], [0, 255, 0], [0, 0, 255]]).unwrap()
+    }
+
+    #[test]
+    fn test_palette_round_trip() {
+        let palette = testing_palette();
+        let chunk = palette.to_chunk();
+        assert_eq!(chunk.data().len(), 9);
+        assert_eq!(Palette::try_from(&chunk).unwrap(), palette);
+        assert!(Palette::try_from(&to_chunk("PLTE", vec![1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_transparency_depends_on_color_type() {
+        let gray = ImageHeader::new(1, 1, 4, ColorType::Grayscale, false).unwrap();
+        let chunk = Transparency::Gray(15).to_chunk();
+        assert_eq!(
+            Transparency::from_chunk(&chunk, &gray).unwrap(),
+            Transparency::Gray(15)
+        );
+        let chunk = Transparency::Gray(16).to_chunk();
+        assert!(Transparency::from_chunk(&chunk, &gray).is_err());
+
+        let rgba = ImageHeader::new(1, 1, 8, ColorType::Rgba, false).unwrap();
+        assert!(Transparency::from_chunk(&chunk, &rgba).is_err());
+    }
+
+    #[test]
+    fn test_suggested_palette_round_trip() {
+        let entry = SuggestedEntry {
+            red: 1,
+            green: 2,
+            blue: 3,
+            alpha: 4,
+            frequency: 5,
+        };
+        for depth in [8, 16] {
+            let suggested = SuggestedPalette::new("web", depth, vec![entry]).unwrap();
+            let decoded = SuggestedPalette::try_from(&suggested.to_chunk()).unwrap();
+            assert_eq!(decoded, suggested);
+        }
+    }
+
+    #[test]
+    fn test_significant_bits_validation() {
+        let header = ImageHeader::new(1, 1, 8, ColorType::Rgb, false).unwrap();
+        assert!(SignificantBits::from_chunk(&to_chunk("sBIT", vec![5, 6, 5]), &header).is_ok());
+        assert!(SignificantBits::from_chunk(&to_chunk("sBIT", vec![5, 6]), &header).is_err());
+        assert!(SignificantBits::from_chunk(&to_chunk("sBIT", vec![5, 9, 5]), &header).is_err());
+    }
+
+    #[test]
+    fn test_valid_indexed_image() {
+        let png = indexed_png(vec![
+            testing_palette().to_chunk(),
+            Transparency::Alpha(vec![0, 128]).to_chunk(),
+            Background::Index(2).to_chunk(),
+            Histogram::new(vec![1, 2, 3]).to_chunk(),
+        ]);
+        let info = PaletteInfo::from_png(&png).unwrap();
+        assert!(info.issues().is_empty(), "{:?}", info.issues());
+        assert_eq!(info.palette().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_inconsistent_indexed_image() {
+        let png = indexed_png(vec![
+            testing_palette().to_chunk(),
+            Transparency::Alpha(vec![0, 0, 0, 0]).to_chunk(),
+            Background::Index(3).to_chunk(),
+            Histogram::new(vec![1, 2]).to_chunk(),
+        ]);
+        let info = PaletteInfo::from_png(&png).unwrap();
+        assert_eq!(info.issues().len(), 3);
+    }
+
+    #[test]
+    fn test_missing_palette() {
+        let png = indexed_png(vec![Background::Index(0).to_chunk()]);
+        let info = PaletteInfo::from_png(&png).unwrap();
+        assert_eq!(
+            info.issues(),
+            ["bKGD must come after PLTE", "Indexed image without PLTE"]
+        );
+    }
+}
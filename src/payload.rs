@@ -0,0 +1,98 @@
+use crate::attachment::{self, Attachment};
+use crate::{compression, crypto, split};
+
+// Layout: MAGIC | version | message
+// Only plain messages that would otherwise be mistaken for one of the framed layers get it
+pub const MAGIC: [u8; 4] = *b"PNGP";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+
+const FRAMED: [[u8; 4]; 5] = [
+    MAGIC,
+    crypto::MAGIC,
+    compression::MAGIC,
+    attachment::MAGIC,
+    split::MAGIC,
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Payload {
+    Message(Vec<u8>),
+    File(Attachment),
+}
+
+impl Payload {
+    // Expects the bytes left once decryption and decompression are undone
+    pub fn from_bytes(data: Vec<u8>) -> crate::Result<Self> {
+        if is_escaped(&data) {
+            if data.len() < HEADER_LEN {
+                return Err("Escaped message is truncated".into());
+            }
+            let version = data[MAGIC.len()];
+            if version != VERSION {
+                return Err(format!("Unsupported escaped message version: {version}").into());
+            }
+            return Ok(Self::Message(data[HEADER_LEN..].to_vec()));
+        }
+        if attachment::is_attachment(&data) {
+            return Ok(Self::File(Attachment::try_from(data.as_slice())?));
+        }
+        Ok(Self::Message(data))
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Message(message) if FRAMED.iter().any(|magic| message.starts_with(magic)) => {
+                let mut bytes = MAGIC.to_vec();
+                bytes.push(VERSION);
+                bytes.extend(message);
+                bytes
+            }
+            Self::Message(message) => message,
+            Self::File(attachment) => attachment.as_bytes(),
+        }
+    }
+}
+
+pub fn is_escaped(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_message_is_unchanged() {
+        let bytes = Payload::Message(b"hello".to_vec()).into_bytes();
+        assert_eq!(bytes, b"hello");
+        assert_eq!(
+            Payload::from_bytes(bytes).unwrap(),
+            Payload::Message(b"hello".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_message_starting_with_magic_round_trips() {
+        for magic in FRAMED {
+            let mut message = magic.to_vec();
+            message.extend(b"\x01 not actually framed");
+            let bytes = Payload::Message(message.clone()).into_bytes();
+            assert!(is_escaped(&bytes));
+            assert_eq!(
+                Payload::from_bytes(bytes).unwrap(),
+                Payload::Message(message)
+            );
+        }
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let attachment = Attachment::new("a.txt", "text/plain", b"hi".to_vec()).unwrap();
+        let bytes = Payload::File(attachment.clone()).into_bytes();
+        assert_eq!(
+            Payload::from_bytes(bytes).unwrap(),
+            Payload::File(attachment)
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Unit {
+    Unknown,
+    Meter,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PhysicalDimensions {
+    x: u32,
+    y: u32,
+    unit: Unit,
+}
+
+impl PhysicalDimensions {
+    pub const CHUNK_TYPE: &'static str = "pHYs";
+
+    pub fn new(x: u32, y: u32, unit: Unit) -> Self {
+        Self { x, y, unit }
+    }
+
+    pub fn from_dpi(dpi: f64) -> crate::Result<Self> {
+        let ppm = (dpi / METERS_PER_INCH).round();
+        if !dpi.is_finite() || dpi <= 0.0 || ppm > u32::MAX as f64 {
+            return Err(format!("DPI out of range: {dpi}").into());
+        }
+        Ok(Self::new(ppm as u32, ppm as u32, Unit::Meter))
+    }
+
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    // Only meaningful when the unit is the meter, otherwise it's just an aspect ratio
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        match self.unit {
+            Unit::Meter => Some((
+                self.x as f64 * METERS_PER_INCH,
+                self.y as f64 * METERS_PER_INCH,
+            )),
+            Unit::Unknown => None,
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let unit = match self.unit {
+            Unit::Unknown => 0,
+            Unit::Meter => 1,
+        };
+        let data = self
+            .x
+            .to_be_bytes()
+            .iter()
+            .chain(self.y.to_be_bytes().iter())
+            .chain([unit].iter())
+            .copied()
+            .collect();
+        Chunk::new(ChunkType::PHYS, data)
+    }
+}
+
+impl TryFrom<&Chunk> for PhysicalDimensions {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(9)?;
+        let data = chunk.data();
+
+        let x = u32::from_be_bytes(data[0..4].try_into()?);
+        let y = u32::from_be_bytes(data[4..8].try_into()?);
+        let unit = match data[8] {
+            0 => Unit::Unknown,
+            1 => Unit::Meter,
+            other => return Err(format!("Invalid pHYs unit specifier: {other}").into()),
+        };
+        Ok(Self { x, y, unit })
+    }
+}
+
+impl Display for PhysicalDimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.dpi() {
+            Some((x_dpi, y_dpi)) => write!(
+                f,
+                "{}x{} pixels per meter ({x_dpi:.1}x{y_dpi:.1} DPI)",
+                self.x, self.y
+            ),
+            None => write!(f, "{}:{} pixel aspect ratio (unit unknown)", self.x, self.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_phys_round_trip() {
+        let phys = PhysicalDimensions::new(3780, 3780, Unit::Meter);
+        let chunk = phys.to_chunk();
+        assert_eq!(chunk.data(), &[0, 0, 14, 196, 0, 0, 14, 196, 1]);
+        assert_eq!(PhysicalDimensions::try_from(&chunk).unwrap(), phys);
+    }
+
+    #[test]
+    fn test_phys_from_dpi() {
+        let phys = PhysicalDimensions::from_dpi(300.0).unwrap();
+        assert_eq!(phys.x(), 11811);
+        assert_eq!(phys.y(), 11811);
+        let (x_dpi, _) = phys.dpi().unwrap();
+        assert!((x_dpi - 300.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_phys_unknown_unit_has_no_dpi() {
+        let phys = PhysicalDimensions::new(1, 2, Unit::Unknown);
+        assert!(phys.dpi().is_none());
+    }
+
+    #[test]
+    fn test_invalid_phys() {
+        assert!(PhysicalDimensions::from_dpi(0.0).is_err());
+        let chunk = Chunk::new(ChunkType::from_str("pHYs").unwrap(), vec![0; 8]);
+        assert!(PhysicalDimensions::try_from(&chunk).is_err());
+        let chunk = Chunk::new(
+            ChunkType::from_str("pHYs").unwrap(),
+            vec![0, 0, 0, 1, 0, 0, 0, 1, 2],
+        );
+        assert!(PhysicalDimensions::try_from(&chunk).is_err());
+    }
+}
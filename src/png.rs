@@ -1,10 +1,13 @@
 use crate::chunk::Chunk;
+use crate::chunk_type::{self, ChunkType};
+use crate::registry::Placement;
 use std::convert::TryFrom;
-use std::io::{BufReader, Read};
 
 #[derive(Debug)]
 pub struct Png {
     chunks: Vec<Chunk>,
+    // Bytes after IEND, kept so rewriting a file doesn't silently drop them
+    trailing: Vec<u8>,
 }
 
 impl std::fmt::Display for Png {
@@ -18,71 +21,231 @@ impl TryFrom<&[u8]> for Png {
     type Error = crate::Error;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Png::parse(bytes, Png::DEFAULT_MAX_ALLOC)
+    }
+}
+
+impl Png {
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const STD_HEADER_LENGTH: usize = 8;
+    // Fixed for the CLI, library users can pass their own limit to parse and parse_lenient
+    pub const DEFAULT_MAX_ALLOC: usize = 1 << 30;
+
+    // Declared lengths are checked against the input before anything is copied, and the
+    // chunk data kept in memory never exceeds max_alloc bytes in total
+    pub fn parse(bytes: &[u8], max_alloc: usize) -> crate::Result<Self> {
         if bytes.len() < Png::STD_HEADER_LENGTH {
             return Err("Png bytes length less than 8".into());
         }
-
-        let mut buf_reader = BufReader::new(bytes);
-        let mut std_header_bytes = [0u8; Png::STD_HEADER_LENGTH];
-        buf_reader.read_exact(&mut std_header_bytes)?;
-
-        let std_header_bytes: [u8; 8] = std_header_bytes.try_into()?;
-        if std_header_bytes != Png::STANDARD_HEADER {
+        let (header, mut rest) = bytes.split_at(Png::STD_HEADER_LENGTH);
+        if header != Png::STANDARD_HEADER {
             return Err("First 8 bytes doesn't correpond to the PNG spec".into());
         }
 
         let mut chunks = Vec::new();
-        let mut data_len_bytes: [u8; 4] = [0; 4];
-
-        while let Ok(()) = buf_reader.read_exact(&mut data_len_bytes) {
-            let cur_chunk_bytes_len = Chunk::CHUNK_TYPE_BYTES_LEN
-                + u32::from_be_bytes(data_len_bytes) as usize
+        let mut allocated = 0usize;
+        while let Some(data_len_bytes) = rest.first_chunk::<{ Chunk::DATA_LEN_BYTES_LEN }>() {
+            let length = u32::from_be_bytes(*data_len_bytes) as usize;
+            if length > Chunk::MAX_LENGTH {
+                return Err(format!(
+                    "Chunk {} declares {length} bytes, over the 2^31-1 limit",
+                    chunks.len()
+                )
+                .into());
+            }
+            let chunk_len = Chunk::DATA_LEN_BYTES_LEN
+                + Chunk::CHUNK_TYPE_BYTES_LEN
+                + length
                 + Chunk::CRC_BYTES_LEN;
+            if chunk_len > rest.len() {
+                return Err(format!(
+                    "Chunk {} declares {length} bytes, more than the file holds",
+                    chunks.len()
+                )
+                .into());
+            }
+            allocated = allocated.saturating_add(length);
+            if allocated > max_alloc {
+                return Err(
+                    format!("Chunk data exceeds the {max_alloc} byte allocation limit").into(),
+                );
+            }
+
+            let (chunk_bytes, tail) = rest.split_at(chunk_len);
+            let chunk = Chunk::try_from(chunk_bytes)?;
+            let is_end = chunk.chunk_type() == &ChunkType::IEND;
+            chunks.push(chunk);
+            rest = tail;
+            if is_end {
+                break;
+            }
+        }
+        // Only what follows IEND is trailing data, anything else is a chunk cut short
+        if !rest.is_empty() && chunks.last().map(Chunk::chunk_type) != Some(&ChunkType::IEND) {
+            return Err(format!(
+                "Chunk {} is truncated, only {} bytes left for its header",
+                chunks.len(),
+                rest.len()
+            )
+            .into());
+        }
+        if allocated.saturating_add(rest.len()) > max_alloc {
+            return Err(
+                format!("Trailing data exceeds the {max_alloc} byte allocation limit").into(),
+            );
+        }
 
-            let mut chunk_bytes = vec![0; usize::try_from(cur_chunk_bytes_len)?];
-
-            buf_reader.read_exact(&mut chunk_bytes)?;
+        Ok(Self {
+            chunks,
+            trailing: rest.to_vec(),
+        })
+    }
 
-            let chunk_bytes: Vec<u8> = data_len_bytes
-                .iter()
-                .chain(chunk_bytes.iter())
-                .copied()
-                .collect();
+    // Keeps going past bad CRCs and invalid types, recording what was wrong with each chunk.
+    // Chunks with a bad CRC are kept with their data, invalid types are skipped.
+    pub fn parse_lenient(bytes: &[u8], max_alloc: usize) -> crate::Result<(Self, Vec<String>)> {
+        if bytes.len() < Png::STD_HEADER_LENGTH {
+            return Err("Png bytes length less than 8".into());
+        }
+        let mut issues = Vec::new();
+        let (header, mut rest) = bytes.split_at(Png::STD_HEADER_LENGTH);
+        if header != Png::STANDARD_HEADER {
+            issues.push("First 8 bytes don't match the PNG signature".to_string());
+        }
 
-            let chunk = Chunk::try_from(chunk_bytes.as_slice())?;
-            chunks.push(chunk)
+        let mut chunks = Vec::new();
+        let mut allocated = 0usize;
+        let mut index = 0;
+        while let Some(data_len_bytes) = rest.first_chunk::<{ Chunk::DATA_LEN_BYTES_LEN }>() {
+            let offset = bytes.len() - rest.len();
+            let length = u32::from_be_bytes(*data_len_bytes) as usize;
+            let chunk_len = Chunk::DATA_LEN_BYTES_LEN
+                + Chunk::CHUNK_TYPE_BYTES_LEN
+                + length
+                + Chunk::CRC_BYTES_LEN;
+            if length > Chunk::MAX_LENGTH || chunk_len > rest.len() {
+                issues.push(format!(
+                    "Chunk {index} at offset {offset} declares {length} bytes but only {} remain, dropped the truncated tail",
+                    rest.len()
+                ));
+                rest = &[];
+                break;
+            }
+            allocated = allocated.saturating_add(length);
+            if allocated > max_alloc {
+                return Err(
+                    format!("Chunk data exceeds the {max_alloc} byte allocation limit").into(),
+                );
+            }
+
+            let (chunk_bytes, tail) = rest.split_at(chunk_len);
+            let (type_bytes, chunk_bytes) = chunk_bytes[Chunk::DATA_LEN_BYTES_LEN..]
+                .split_first_chunk::<{ Chunk::CHUNK_TYPE_BYTES_LEN }>()
+                .ok_or("Chunk type bytes missing")?;
+            let (data, crc_bytes) = chunk_bytes.split_at(length);
+            let crc = u32::from_be_bytes(crc_bytes.try_into()?);
+            match ChunkType::try_from(*type_bytes) {
+                Ok(chunk_type) => {
+                    if !chunk_type.is_reserved_bit_valid() {
+                        issues.push(format!(
+                            "Chunk {index} ({chunk_type}) at offset {offset} has the reserved bit set"
+                        ));
+                    }
+                    let chunk = Chunk::new(chunk_type, data.to_vec());
+                    if chunk.crc() != crc {
+                        issues.push(format!(
+                            "Chunk {index} ({}) at offset {offset} has a bad CRC, kept its data",
+                            chunk.chunk_type()
+                        ));
+                    }
+                    let is_end = chunk.chunk_type() == &ChunkType::IEND;
+                    chunks.push(chunk);
+                    if is_end {
+                        rest = tail;
+                        break;
+                    }
+                }
+                Err(_) => issues.push(format!(
+                    "Chunk {index} at offset {offset} has an invalid type {}, skipped",
+                    chunk_type::escape(type_bytes)
+                )),
+            }
+            rest = tail;
+            index += 1;
+        }
+        if chunks.last().map(Chunk::chunk_type) == Some(&ChunkType::IEND) {
+            if !rest.is_empty() {
+                issues.push(format!("{} bytes of trailing data after IEND", rest.len()));
+            }
+        } else if !rest.is_empty() {
+            issues.push(format!("{} stray bytes after the last chunk", rest.len()));
+        }
+        if allocated.saturating_add(rest.len()) > max_alloc {
+            return Err(
+                format!("Trailing data exceeds the {max_alloc} byte allocation limit").into(),
+            );
         }
 
-        Ok(Self { chunks })
+        Ok((
+            Self {
+                chunks,
+                trailing: rest.to_vec(),
+            },
+            issues,
+        ))
     }
-}
-
-impl Png {
-    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
-    pub const STD_HEADER_LENGTH: usize = 8;
 
     pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
-        Self { chunks }
+        Self {
+            chunks,
+            trailing: Vec::new(),
+        }
     }
 
     pub fn append_chunk(&mut self, chunk: Chunk) {
         // Making sure IEND is always the last chunk
-        self.chunks.insert(self.chunks.len() - 1, chunk)
+        self.chunks
+            .insert(self.chunks.len().saturating_sub(1), chunk)
     }
 
     pub fn remove_chunk(&mut self, chunk_type: &str) -> crate::Result<Chunk> {
-        let res: Option<(usize, &Chunk)> = self
+        let idx = self
             .chunks
             .iter()
-            .enumerate()
-            .find(|(idx, chunk)| chunk.chunk_type().to_string() == chunk_type);
-        if res.is_none() {
-            return Err(format!("Chunk with type {chunk_type} not present").into());
+            .position(|chunk| chunk.chunk_type().to_string() == chunk_type);
+        match idx {
+            None => Err(format!("Chunk with type {chunk_type} not present").into()),
+            // Removing in place keeps the remaining chunks (and IEND) in order
+            Some(idx) => Ok(self.chunks.remove(idx)),
+        }
+    }
+
+    pub fn insert_before_image_data(&mut self, chunk: Chunk) {
+        // tRNS, bKGD and hIST describe palette entries, so they have to follow PLTE
+        let after_palette = chunk
+            .chunk_type()
+            .known_info()
+            .is_some_and(|info| info.placement == Placement::AfterPaletteBeforeImageData);
+        let idx = self
+            .chunks
+            .iter()
+            .position(|existing| {
+                let chunk_type = existing.chunk_type().to_string();
+                chunk_type == "IDAT" || (chunk_type == "PLTE" && !after_palette)
+            })
+            .unwrap_or(self.chunks.len().saturating_sub(1));
+        self.chunks.insert(idx, chunk)
+    }
+
+    pub fn set_chunk(&mut self, chunk: Chunk) {
+        let existing = self
+            .chunks
+            .iter()
+            .position(|c| c.chunk_type() == chunk.chunk_type());
+        match existing {
+            Some(idx) => self.chunks[idx] = chunk,
+            None => self.insert_before_image_data(chunk),
         }
-        let res = res.unwrap();
-        let chunk = res.1.to_owned();
-        self.chunks.swap_remove(res.0);
-        Ok(chunk)
     }
 
     pub fn header(&self) -> &[u8; 8] {
@@ -99,16 +262,47 @@ impl Png {
             .find(|chunk| chunk.chunk_type().to_string() == chunk_type)
     }
 
+    pub fn retain_chunks(&mut self, keep: impl FnMut(&Chunk) -> bool) {
+        self.chunks.retain(keep)
+    }
+
+    pub fn trailing(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    // Offset of the trailing data in the serialized file
+    pub fn trailing_offset(&self) -> usize {
+        Png::STD_HEADER_LENGTH
+            + self
+                .chunks
+                .iter()
+                .map(|chunk| {
+                    Chunk::DATA_LEN_BYTES_LEN
+                        + Chunk::CHUNK_TYPE_BYTES_LEN
+                        + chunk.length() as usize
+                        + Chunk::CRC_BYTES_LEN
+                })
+                .sum::<usize>()
+    }
+
+    pub fn strip_trailing(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing)
+    }
+
+    pub fn set_trailing(&mut self, trailing: Vec<u8>) {
+        self.trailing = trailing;
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
-        println!("{self:?}");
         let chunk_bytes: Vec<u8> = self
             .chunks
             .iter()
             .flat_map(|chunk| chunk.as_bytes())
             .collect();
-        Png::STANDARD_HEADER
+        self.header()
             .iter()
             .chain(chunk_bytes.iter())
+            .chain(self.trailing.iter())
             .copied()
             .collect()
     }
@@ -241,6 +435,50 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_set_chunk_before_image_data() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "pixels").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.set_chunk(chunk_from_strings("pHYs", "first").unwrap());
+        png.set_chunk(chunk_from_strings("pHYs", "second").unwrap());
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "pHYs", "IDAT", "IEND"]);
+        let chunk = png.chunk_by_type("pHYs").unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "second");
+    }
+
+    #[test]
+    fn test_set_chunk_in_palette_image() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("PLTE", "palette").unwrap(),
+            chunk_from_strings("IDAT", "pixels").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        for chunk_type in ["tRNS", "gAMA", "bKGD", "pHYs", "hIST"] {
+            png.set_chunk(chunk_from_strings(chunk_type, "data").unwrap());
+        }
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "gAMA", "pHYs", "PLTE", "tRNS", "bKGD", "hIST", "IDAT", "IEND"]
+        );
+        assert!(crate::registry::ordering_issues(&png).is_empty());
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -519,4 +757,221 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
+
+    #[test]
+    fn test_lenient_parse_keeps_bad_crc_and_skips_invalid_types() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"salvage me".to_vec()),
+            Chunk::new(ChunkType::from_str("abCd").unwrap(), vec![1, 2]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        let mut bytes = png.as_bytes();
+        // Flip a data byte of ruSt and a type byte of abCd
+        let rust_offset = 8 + 12 + 13;
+        bytes[rust_offset + 8] ^= 0x01;
+        let abcd_offset = rust_offset + 12 + 10;
+        bytes[abcd_offset + 4] = b'1';
+        bytes.extend([0, 0]);
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+
+        let (recovered, issues) = Png::parse_lenient(&bytes, Png::DEFAULT_MAX_ALLOC).unwrap();
+        let types: Vec<String> = recovered
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+        assert_eq!(recovered.chunks()[1].data(), b"ralvage me");
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].contains("bad CRC"));
+        assert!(issues[1].contains("1bCd"));
+        assert!(issues[2].contains("2 bytes of trailing data"));
+        assert_eq!(recovered.trailing(), [0, 0]);
+    }
+
+    #[test]
+    fn test_lenient_parse_drops_truncated_tail() {
+        let bytes = testing_png().as_bytes();
+        let (recovered, issues) =
+            Png::parse_lenient(&bytes[..bytes.len() - 5], Png::DEFAULT_MAX_ALLOC).unwrap();
+        assert_eq!(recovered.chunks().len(), 2);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("truncated tail"));
+    }
+
+    #[test]
+    fn test_trailing_data_after_iend() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        let mut bytes = png.as_bytes();
+        bytes.extend(b"PK\x03\x04 hidden zip");
+        let mut parsed = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.chunks().len(), 2);
+        assert_eq!(parsed.trailing_offset(), png.as_bytes().len());
+        assert_eq!(parsed.as_bytes(), bytes);
+        assert_eq!(parsed.strip_trailing(), b"PK\x03\x04 hidden zip");
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_short_leftover_without_iend_is_truncation() {
+        let png = Png::from_chunks(vec![Chunk::new(ChunkType::IHDR, vec![0; 13])]);
+        let mut bytes = png.as_bytes();
+        bytes.extend([0, 0, 0]);
+        let error = Png::try_from(bytes.as_slice()).unwrap_err().to_string();
+        assert!(error.contains("truncated"), "{error}");
+    }
+
+    #[test]
+    fn test_allocation_limit() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IDAT, vec![0; 100]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        let bytes = png.as_bytes();
+        assert!(Png::parse(&bytes, 113).is_ok());
+        assert!(Png::parse(&bytes, 112).is_err());
+    }
+
+    #[test]
+    fn test_length_over_spec_limit() {
+        let mut bytes = Png::STANDARD_HEADER.to_vec();
+        bytes.extend((1u32 << 31).to_be_bytes());
+        bytes.extend(b"ruSt");
+        let err = Png::try_from(bytes.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("2^31-1"));
+    }
+}
+
+// Property tests feed generated files through every decoder, they only check nothing panics
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::apng::Animation;
+    use crate::color::ColorSpace;
+    use crate::ihdr::{ColorType, ImageHeader};
+    use crate::inspect::Inspection;
+    use crate::palette::PaletteInfo;
+    use crate::registry::KNOWN_CHUNKS;
+    use crate::{attachment, carve, compression, json, repair, signature, split, validate};
+    use proptest::prelude::*;
+
+    // Known chunk types with arbitrary contents reach every typed decoder
+    fn arbitrary_chunk() -> impl Strategy<Value = Chunk> {
+        let known = (0..KNOWN_CHUNKS.len()).prop_map(|idx| {
+            ChunkType::try_from(
+                <[u8; 4]>::try_from(KNOWN_CHUNKS[idx].chunk_type.as_bytes()).unwrap(),
+            )
+            .unwrap()
+        });
+        let any_type =
+            proptest::array::uniform4(proptest::char::range('a', 'z')).prop_map(|letters| {
+                let mut bytes = letters.map(|c| c as u8);
+                bytes[2].make_ascii_uppercase();
+                ChunkType::try_from(bytes).unwrap()
+            });
+        (
+            prop_oneof![3 => known, 1 => any_type],
+            prop_oneof![Just(1usize), Just(4), Just(13), Just(26), 0..64usize]
+                .prop_flat_map(|len| proptest::collection::vec(any::<u8>(), len)),
+        )
+            .prop_map(|(chunk_type, data)| Chunk::new(chunk_type, data))
+    }
+
+    // A well formed IHDR gets the header dependent decoders past their first check
+    fn valid_header() -> impl Strategy<Value = Chunk> {
+        let color_types = [
+            ColorType::Grayscale,
+            ColorType::Rgb,
+            ColorType::Indexed,
+            ColorType::GrayscaleAlpha,
+            ColorType::Rgba,
+        ];
+        (
+            1..64u32,
+            1..64u32,
+            0..color_types.len(),
+            any::<usize>(),
+            any::<bool>(),
+        )
+            .prop_map(move |(width, height, color, depth, interlaced)| {
+                let color_type = color_types[color];
+                let depths = color_type.allowed_bit_depths();
+                ImageHeader::new(
+                    width,
+                    height,
+                    depths[depth % depths.len()],
+                    color_type,
+                    interlaced,
+                )
+                .unwrap()
+                .to_chunk()
+            })
+    }
+
+    fn with_header(tail: &[u8]) -> Vec<u8> {
+        Png::STANDARD_HEADER.iter().chain(tail).copied().collect()
+    }
+
+    proptest! {
+        #[test]
+        fn parsing_arbitrary_bytes_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let _ = Png::try_from(bytes.as_slice());
+            let _ = Png::try_from(with_header(&bytes).as_slice());
+            let _ = Inspection::scan(&with_header(&bytes));
+            let _ = Chunk::try_from(bytes.as_slice());
+            let _ = Png::parse_lenient(&with_header(&bytes), Png::DEFAULT_MAX_ALLOC);
+            let _ = carve::carve(&with_header(&bytes));
+            let _ = repair::repair(&with_header(&bytes), false);
+        }
+
+        #[test]
+        fn hostile_lengths_are_rejected(length in any::<u32>(), tail in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let mut bytes = with_header(&length.to_be_bytes());
+            bytes.extend(b"ruSt");
+            bytes.extend(&tail);
+            prop_assume!(length as usize + 4 > tail.len());
+            prop_assert!(Png::try_from(bytes.as_slice()).is_err());
+        }
+
+        #[test]
+        fn decoding_arbitrary_chunks_never_panics(
+            header in valid_header(),
+            chunks in proptest::collection::vec(arbitrary_chunk(), 0..12),
+        ) {
+            let png = Png::from_chunks([header].into_iter().chain(chunks).collect());
+            let _ = validate::validate(&png);
+            let _ = json::png(&png);
+            let header = ImageHeader::from_png(&png).ok();
+            let _ = PaletteInfo::from_png(&png).map(|info| info.to_string());
+            let _ = ColorSpace::from_png(&png).to_string();
+            let _ = signature::signatures(&png);
+            if let Ok(animation) = Animation::from_png(&png) {
+                for idx in 0..animation.frames().len() {
+                    let _ = animation.frame_png(idx);
+                }
+            }
+            for chunk in png.chunks() {
+                if let Some(info) = chunk.chunk_type().known_info() {
+                    let _ = info.pretty_print(chunk, header.as_ref());
+                }
+            }
+            let reparsed = Png::try_from(png.as_bytes().as_slice()).unwrap();
+            prop_assert_eq!(reparsed.as_bytes(), png.as_bytes());
+        }
+
+        #[test]
+        fn decoding_arbitrary_payloads_never_panics(data in proptest::collection::vec(any::<u8>(), 0..128)) {
+            for magic in [compression::MAGIC, attachment::MAGIC, split::MAGIC] {
+                let payload: Vec<u8> = magic.iter().chain(&data).copied().collect();
+                let _ = compression::decompress(&payload);
+                let _ = attachment::Attachment::try_from(payload.as_slice());
+                let _ = split::reassemble(&[payload.as_slice(), data.as_slice()]);
+            }
+        }
+    }
 }
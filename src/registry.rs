@@ -0,0 +1,458 @@
+use crate::apng::{AnimationControl, FrameControl};
+use crate::chunk::Chunk;
+use crate::color::{Chromaticities, Cicp, Gamma, RenderingIntent};
+use crate::ihdr::ImageHeader;
+use crate::palette::{
+    Background, Histogram, Palette, SignificantBits, SuggestedPalette, Transparency,
+};
+use crate::phys::PhysicalDimensions;
+use crate::png::Png;
+use crate::signature::Signature;
+use flate2::read::ZlibDecoder;
+use std::fmt::Display;
+use std::io::Read;
+
+const APNG_SPEC: &str = "PNG 3rd edition (APNG)";
+// Text previews are only for display, so inflate at most this much
+const MAX_TEXT_LEN: u64 = 4096;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Placement {
+    First,
+    Last,
+    BeforePalette,
+    AfterPaletteBeforeImageData,
+    BeforeImageData,
+    ImageData,
+    Anywhere,
+}
+
+impl Display for Placement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::First => "must be first",
+            Self::Last => "must be last",
+            Self::BeforePalette => "before PLTE and IDAT",
+            Self::AfterPaletteBeforeImageData => "after PLTE, before IDAT",
+            Self::BeforeImageData => "before IDAT",
+            Self::ImageData => "consecutive IDAT run",
+            Self::Anywhere => "anywhere",
+        };
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PrettyPrinter {
+    Standalone(fn(&Chunk) -> crate::Result<String>),
+    // tRNS, bKGD and sBIT can't be read without the colour type and bit depth
+    NeedsHeader(fn(&Chunk, &ImageHeader) -> crate::Result<String>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkInfo {
+    pub chunk_type: &'static str,
+    pub name: &'static str,
+    pub spec: &'static str,
+    pub placement: Placement,
+    pub multiple: bool,
+    pub pretty: Option<PrettyPrinter>,
+}
+
+impl ChunkInfo {
+    const fn new(
+        chunk_type: &'static str,
+        name: &'static str,
+        spec: &'static str,
+        placement: Placement,
+        multiple: bool,
+        pretty: Option<PrettyPrinter>,
+    ) -> Self {
+        Self {
+            chunk_type,
+            name,
+            spec,
+            placement,
+            multiple,
+            pretty,
+        }
+    }
+
+    pub fn pretty_print(
+        &self,
+        chunk: &Chunk,
+        header: Option<&ImageHeader>,
+    ) -> Option<crate::Result<String>> {
+        self.pretty.map(|pretty| match pretty {
+            Standalone(pretty) => pretty(chunk),
+            NeedsHeader(pretty) => pretty(chunk, header.ok_or("decoding needs a valid IHDR")?),
+        })
+    }
+}
+
+use Placement::*;
+use PrettyPrinter::*;
+
+#[rustfmt::skip]
+pub const KNOWN_CHUNKS: [ChunkInfo; 30] = [
+    ChunkInfo::new("IHDR", "Image header", "PNG 11.2.1", First, false, Some(Standalone(display::<ImageHeader>))),
+    ChunkInfo::new("PLTE", "Palette", "PNG 11.2.2", BeforeImageData, false, Some(Standalone(palette))),
+    ChunkInfo::new("IDAT", "Image data", "PNG 11.2.3", ImageData, true, None),
+    ChunkInfo::new("IEND", "Image trailer", "PNG 11.2.4", Last, false, None),
+    ChunkInfo::new("tRNS", "Transparency", "PNG 11.3.1.1", AfterPaletteBeforeImageData, false, Some(NeedsHeader(transparency))),
+    ChunkInfo::new("cHRM", "Primary chromaticities and white point", "PNG 11.3.2.1", BeforePalette, false, Some(Standalone(display::<Chromaticities>))),
+    ChunkInfo::new("gAMA", "Image gamma", "PNG 11.3.2.2", BeforePalette, false, Some(Standalone(display::<Gamma>))),
+    ChunkInfo::new("iCCP", "Embedded ICC profile", "PNG 11.3.2.3", BeforePalette, false, Some(Standalone(icc_profile))),
+    ChunkInfo::new("sBIT", "Significant bits", "PNG 11.3.2.4", BeforePalette, false, Some(NeedsHeader(significant_bits))),
+    ChunkInfo::new("sRGB", "Standard RGB colour space", "PNG 11.3.2.5", BeforePalette, false, Some(Standalone(display::<RenderingIntent>))),
+    ChunkInfo::new("cICP", "Coding-independent code points", "PNG 11.3.2.6", BeforePalette, false, Some(Standalone(display::<Cicp>))),
+    ChunkInfo::new("mDCV", "Mastering display colour volume", "PNG 11.3.2.7", BeforePalette, false, None),
+    ChunkInfo::new("cLLI", "Content light level information", "PNG 11.3.2.8", BeforePalette, false, None),
+    ChunkInfo::new("tEXt", "Textual data", "PNG 11.3.3.2", Anywhere, true, Some(Standalone(text))),
+    ChunkInfo::new("zTXt", "Compressed textual data", "PNG 11.3.3.3", Anywhere, true, Some(Standalone(compressed_text))),
+    ChunkInfo::new("iTXt", "International textual data", "PNG 11.3.3.4", Anywhere, true, Some(Standalone(international_text))),
+    ChunkInfo::new("bKGD", "Background colour", "PNG 11.3.4.1", AfterPaletteBeforeImageData, false, Some(NeedsHeader(background))),
+    ChunkInfo::new("hIST", "Image histogram", "PNG 11.3.4.2", AfterPaletteBeforeImageData, false, Some(Standalone(histogram))),
+    ChunkInfo::new("pHYs", "Physical pixel dimensions", "PNG 11.3.4.3", BeforeImageData, false, Some(Standalone(display::<PhysicalDimensions>))),
+    ChunkInfo::new("sPLT", "Suggested palette", "PNG 11.3.4.4", BeforeImageData, true, Some(Standalone(suggested_palette))),
+    ChunkInfo::new("eXIf", "Exchangeable image file profile", "PNG 11.3.4.5", BeforeImageData, false, None),
+    ChunkInfo::new("tIME", "Image last-modification time", "PNG 11.3.4.6", Anywhere, false, Some(Standalone(time))),
+    ChunkInfo::new("acTL", "Animation control", APNG_SPEC, BeforeImageData, false, Some(Standalone(animation_control))),
+    ChunkInfo::new("fcTL", "Frame control", APNG_SPEC, Anywhere, true, Some(Standalone(frame_control))),
+    ChunkInfo::new("fdAT", "Frame data", APNG_SPEC, Anywhere, true, None),
+    ChunkInfo::new("oFFs", "Image offset", "PNG extensions 1.2.0", BeforeImageData, false, None),
+    ChunkInfo::new("pCAL", "Pixel calibration", "PNG extensions 1.2.0", BeforeImageData, false, None),
+    ChunkInfo::new("sCAL", "Physical scale", "PNG extensions 1.2.0", BeforeImageData, false, None),
+    ChunkInfo::new("sTER", "Stereo image indicator", "PNG extensions 1.3.0", BeforeImageData, false, None),
+    ChunkInfo::new("CgBI", "Apple optimized PNG (non-standard)", "Apple Xcode pngcrush", First, false, None),
+];
+
+// Chunks pngme writes itself, known to the tools but not part of any PNG specification
+#[rustfmt::skip]
+pub const EXTENSION_CHUNKS: [ChunkInfo; 1] = [
+    ChunkInfo::new("siGN", "pngme signature", "pngme extension", Anywhere, true, Some(Standalone(signature))),
+];
+
+pub fn lookup(chunk_type: &[u8; 4]) -> Option<&'static ChunkInfo> {
+    lookup_standard(chunk_type).or_else(|| {
+        EXTENSION_CHUNKS
+            .iter()
+            .find(|info| info.chunk_type.as_bytes() == chunk_type)
+    })
+}
+
+pub fn lookup_standard(chunk_type: &[u8; 4]) -> Option<&'static ChunkInfo> {
+    KNOWN_CHUNKS
+        .iter()
+        .find(|info| info.chunk_type.as_bytes() == chunk_type)
+}
+
+// Checks the placement rules of every known chunk in the file
+pub fn ordering_issues(png: &Png) -> Vec<String> {
+    let types: Vec<[u8; 4]> = png
+        .chunks()
+        .iter()
+        .map(|chunk| chunk.chunk_type().bytes())
+        .collect();
+    let first = |chunk_type: &[u8; 4]| types.iter().position(|t| t == chunk_type);
+    let palette = first(b"PLTE");
+    let image_data = first(b"IDAT");
+    // CgBI files put their own chunk before IHDR
+    let header_idx = if types.first() == Some(b"CgBI") { 1 } else { 0 };
+
+    let mut issues = Vec::new();
+    for (idx, chunk_type) in types.iter().enumerate() {
+        let info = match lookup_standard(chunk_type) {
+            Some(info) => info,
+            None => continue,
+        };
+        let name = info.chunk_type;
+        let before = |limit: Option<usize>| limit.is_none_or(|limit| idx < limit);
+        let issue = match info.placement {
+            First if name == "IHDR" && idx != header_idx => {
+                Some(format!("{name} must be the first chunk"))
+            }
+            First if name != "IHDR" && idx != 0 => Some(format!("{name} must be the first chunk")),
+            Last if idx != types.len() - 1 => Some(format!("{name} must be the last chunk")),
+            BeforePalette if !before(palette) => Some(format!("{name} must come before PLTE")),
+            BeforePalette | AfterPaletteBeforeImageData | BeforeImageData
+                if !before(image_data) =>
+            {
+                Some(format!("{name} must come before the first IDAT chunk"))
+            }
+            AfterPaletteBeforeImageData if palette.is_some_and(|palette| idx < palette) => {
+                Some(format!("{name} must come after PLTE"))
+            }
+            _ => None,
+        };
+        issues.extend(issue);
+        // Reported once, on the second occurrence
+        if !info.multiple && types[..idx].iter().filter(|t| *t == chunk_type).count() == 1 {
+            issues.push(format!("More than one {name} chunk"));
+        }
+    }
+    issues
+}
+
+fn display<T>(chunk: &Chunk) -> crate::Result<String>
+where
+    T: for<'a> TryFrom<&'a Chunk, Error = crate::Error> + Display,
+{
+    Ok(T::try_from(chunk)?.to_string())
+}
+
+fn animation_control(chunk: &Chunk) -> crate::Result<String> {
+    let control = AnimationControl::try_from(chunk)?;
+    let plays = match control.num_plays() {
+        0 => "looping forever".to_string(),
+        plays => format!("{plays} plays"),
+    };
+    Ok(format!("{} frames, {plays}", control.num_frames()))
+}
+
+fn frame_control(chunk: &Chunk) -> crate::Result<String> {
+    let control = FrameControl::try_from(chunk)?;
+    Ok(format!(
+        "#{} {}x{} at ({}, {}), {:.3}s",
+        control.sequence_number,
+        control.width,
+        control.height,
+        control.x_offset,
+        control.y_offset,
+        control.delay()
+    ))
+}
+
+// Keywords and tEXt strings are Latin-1, which maps byte for byte onto chars
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn split_keyword(data: &[u8]) -> crate::Result<(String, &[u8])> {
+    let end = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or("Missing keyword terminator")?;
+    if end == 0 || end > 79 {
+        return Err(format!("Keyword must be 1 to 79 bytes, found {end}").into());
+    }
+    Ok((latin1(&data[..end]), &data[end + 1..]))
+}
+
+fn inflate(data: &[u8]) -> crate::Result<String> {
+    let mut text = Vec::new();
+    ZlibDecoder::new(data)
+        .take(MAX_TEXT_LEN)
+        .read_to_end(&mut text)?;
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+fn text(chunk: &Chunk) -> crate::Result<String> {
+    let (keyword, text) = split_keyword(chunk.data())?;
+    Ok(format!("{keyword}: {}", latin1(text)))
+}
+
+fn compressed_text(chunk: &Chunk) -> crate::Result<String> {
+    let (keyword, rest) = split_keyword(chunk.data())?;
+    match rest.split_first() {
+        Some((0, compressed)) => Ok(format!("{keyword}: {}", inflate(compressed)?)),
+        Some((method, _)) => Err(format!("Unknown zTXt compression method {method}").into()),
+        None => Err("zTXt is missing its compression method".into()),
+    }
+}
+
+fn international_text(chunk: &Chunk) -> crate::Result<String> {
+    let (keyword, rest) = split_keyword(chunk.data())?;
+    if rest.len() < 2 {
+        return Err("iTXt is missing its compression fields".into());
+    }
+    let (compressed, rest) = (rest[0] == 1, &rest[2..]);
+    let mut fields = rest.splitn(3, |&b| b == 0);
+    let language = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+    let _translated = fields.next();
+    let text = fields.next().ok_or("iTXt is missing its text")?;
+    let text = if compressed {
+        inflate(text)?
+    } else {
+        String::from_utf8_lossy(text).into_owned()
+    };
+    match language.is_empty() {
+        true => Ok(format!("{keyword}: {text}")),
+        false => Ok(format!("{keyword} [{language}]: {text}")),
+    }
+}
+
+fn icc_profile(chunk: &Chunk) -> crate::Result<String> {
+    let (name, rest) = split_keyword(chunk.data())?;
+    Ok(format!(
+        "{name} ({} compressed bytes)",
+        rest.len().saturating_sub(1)
+    ))
+}
+
+fn palette(chunk: &Chunk) -> crate::Result<String> {
+    Ok(format!("{} entries", Palette::try_from(chunk)?.len()))
+}
+
+fn transparency(chunk: &Chunk, header: &ImageHeader) -> crate::Result<String> {
+    Ok(match Transparency::from_chunk(chunk, header)? {
+        Transparency::Gray(gray) => format!("gray {gray}"),
+        Transparency::Rgb([r, g, b]) => format!("rgb({r}, {g}, {b})"),
+        Transparency::Alpha(alpha) => format!("{} palette alpha values", alpha.len()),
+    })
+}
+
+fn background(chunk: &Chunk, header: &ImageHeader) -> crate::Result<String> {
+    Ok(Background::from_chunk(chunk, header)?.to_string())
+}
+
+fn histogram(chunk: &Chunk) -> crate::Result<String> {
+    let histogram = Histogram::try_from(chunk)?;
+    Ok(format!("{} frequencies", histogram.frequencies().len()))
+}
+
+fn significant_bits(chunk: &Chunk, header: &ImageHeader) -> crate::Result<String> {
+    let bits = SignificantBits::from_chunk(chunk, header)?;
+    let bits: Vec<String> = bits.bits().iter().map(u8::to_string).collect();
+    Ok(bits.join(", "))
+}
+
+fn suggested_palette(chunk: &Chunk) -> crate::Result<String> {
+    let palette = SuggestedPalette::try_from(chunk)?;
+    Ok(format!(
+        "{}, {}-bit, {} entries",
+        palette.name(),
+        palette.sample_depth(),
+        palette.entries().len()
+    ))
+}
+
+fn signature(chunk: &Chunk) -> crate::Result<String> {
+    let signature = Signature::try_from(chunk)?;
+    let covers = if signature.covers_image() {
+        " and the image data"
+    } else {
+        ""
+    };
+    Ok(format!(
+        "covers {}{covers}, signed by {}",
+        signature.target(),
+        signature.key()
+    ))
+}
+
+fn time(chunk: &Chunk) -> crate::Result<String> {
+    match chunk.data() {
+        [y1, y2, month, day, hour, minute, second] => Ok(format!(
+            "{:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} UTC",
+            u16::from_be_bytes([*y1, *y2])
+        )),
+        data => Err(format!("Invalid tIME data length {}", data.len()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::ihdr::ColorType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_known_info() {
+        let info = ChunkType::from_str("tEXt").unwrap().known_info().unwrap();
+        assert_eq!(info.name, "Textual data");
+        assert!(info.multiple);
+        assert!(ChunkType::from_str("ruSt").unwrap().known_info().is_none());
+    }
+
+    #[test]
+    fn test_registry_has_unique_valid_types() {
+        let all: Vec<&ChunkInfo> = KNOWN_CHUNKS.iter().chain(&EXTENSION_CHUNKS).collect();
+        for (idx, info) in all.iter().enumerate() {
+            assert!(ChunkType::from_str(info.chunk_type).is_ok());
+            assert!(all[idx + 1..]
+                .iter()
+                .all(|other| other.chunk_type != info.chunk_type));
+        }
+    }
+
+    #[test]
+    fn test_extension_chunks_are_not_standard() {
+        assert_eq!(lookup(b"siGN").unwrap().spec, "pngme extension");
+        assert!(lookup_standard(b"siGN").is_none());
+    }
+
+    #[test]
+    fn test_palette_pretty_printers() {
+        let header = ImageHeader::new(1, 1, 8, ColorType::Indexed, false).unwrap();
+        let print = |chunk_type: &str, data: &[u8], header: Option<&ImageHeader>| {
+            let info = lookup(chunk_type.as_bytes().try_into().unwrap()).unwrap();
+            info.pretty_print(&chunk(chunk_type, data), header).unwrap()
+        };
+        assert_eq!(print("PLTE", &[0; 6], None).unwrap(), "2 entries");
+        assert_eq!(
+            print("tRNS", &[0, 255], Some(&header)).unwrap(),
+            "2 palette alpha values"
+        );
+        assert_eq!(
+            print("bKGD", &[1], Some(&header)).unwrap(),
+            "palette index 1"
+        );
+        assert_eq!(print("hIST", &[0, 1, 0, 2], None).unwrap(), "2 frequencies");
+        assert_eq!(print("sBIT", &[5, 6, 5], Some(&header)).unwrap(), "5, 6, 5");
+        assert_eq!(
+            print("sPLT", b"web\0\x08\0\0\0\xff\0\x01", None).unwrap(),
+            "web, 8-bit, 1 entries"
+        );
+        assert!(print("tRNS", &[0, 255], None).is_err());
+    }
+
+    #[test]
+    fn test_text_pretty_printers() {
+        let info = lookup(b"tEXt").unwrap();
+        let printed = info
+            .pretty_print(&chunk("tEXt", b"Title\0Dice"), None)
+            .unwrap();
+        assert_eq!(printed.unwrap(), "Title: Dice");
+
+        let mut data = b"Comment\0\0".to_vec();
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut encoder, b"squeezed").unwrap();
+        data.extend(encoder.finish().unwrap());
+        assert_eq!(
+            compressed_text(&chunk("zTXt", &data)).unwrap(),
+            "Comment: squeezed"
+        );
+
+        let itxt = chunk("iTXt", b"Title\0\0\0fr\0Titre\0D\xc3\xa9s");
+        assert_eq!(international_text(&itxt).unwrap(), "Title [fr]: Dés");
+        assert!(text(&chunk("tEXt", b"no terminator")).is_err());
+    }
+
+    #[test]
+    fn test_time_pretty_printer() {
+        let tm = chunk("tIME", &[0x07, 0xea, 10, 18, 9, 5, 0]);
+        assert_eq!(time(&tm).unwrap(), "2026-10-18 09:05:00 UTC");
+    }
+
+    #[test]
+    fn test_ordering_issues() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", &[]),
+            chunk("gAMA", &[0, 0, 0, 1]),
+            chunk("PLTE", &[0; 3]),
+            chunk("gAMA", &[0, 0, 0, 1]),
+            chunk("IEND", &[]),
+        ]);
+        let issues = ordering_issues(&png);
+        assert!(issues.contains(&"gAMA must come before the first IDAT chunk".to_string()));
+        assert!(issues.contains(&"PLTE must come before the first IDAT chunk".to_string()));
+        assert!(issues.contains(&"More than one gAMA chunk".to_string()));
+    }
+}
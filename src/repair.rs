@@ -0,0 +1,310 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::{self, ChunkType};
+use crate::png::Png;
+use crate::registry;
+use std::fmt::Display;
+
+const OVERHEAD: usize =
+    Chunk::DATA_LEN_BYTES_LEN + Chunk::CHUNK_TYPE_BYTES_LEN + Chunk::CRC_BYTES_LEN;
+const CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+#[derive(Debug)]
+pub struct Repair {
+    png: Png,
+    fixes: Vec<String>,
+}
+
+impl Repair {
+    pub fn png(&self) -> &Png {
+        &self.png
+    }
+
+    pub fn into_png(self) -> Png {
+        self.png
+    }
+
+    pub fn fixes(&self) -> &[String] {
+        &self.fixes
+    }
+}
+
+impl Display for Repair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fixes.is_empty() {
+            return writeln!(f, "Nothing to repair");
+        }
+        writeln!(f, "Repaired {} problem(s):", self.fixes.len())?;
+        for fix in &self.fixes {
+            writeln!(f, "  {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+// A chunk header as found in the file, before anything about it is trusted
+struct RawChunk<'a> {
+    type_bytes: [u8; 4],
+    data: &'a [u8],
+    crc: u32,
+}
+
+impl<'a> RawChunk<'a> {
+    // Only checks that the declared length fits, the type and CRC may still be wrong
+    fn read(bytes: &'a [u8], offset: usize) -> Option<Self> {
+        let rest = bytes.get(offset..)?;
+        let length = u32::from_be_bytes(*rest.first_chunk::<4>()?) as usize;
+        if length > Chunk::MAX_LENGTH || OVERHEAD + length > rest.len() {
+            return None;
+        }
+        let type_bytes = rest[4..8].try_into().ok()?;
+        let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().ok()?);
+        Some(Self {
+            type_bytes,
+            data: &rest[8..8 + length],
+            crc,
+        })
+    }
+
+    // A set reserved bit is far more likely corruption than a real chunk
+    fn chunk_type(&self) -> Option<ChunkType> {
+        ChunkType::checked(self.type_bytes).filter(ChunkType::is_valid)
+    }
+
+    // Checked straight on the file bytes, resync tries this at every offset
+    fn crc_valid(&self) -> bool {
+        if self.chunk_type().is_none() {
+            return false;
+        }
+        let mut digest = CRC.digest();
+        digest.update(&self.type_bytes);
+        digest.update(self.data);
+        digest.finalize() == self.crc
+    }
+
+    fn len(&self) -> usize {
+        OVERHEAD + self.data.len()
+    }
+}
+
+// Prefers a chunk whose CRC checks out, then falls back to the first known chunk type that fits
+fn resync(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut fallback = None;
+    for offset in from..bytes.len() {
+        let Some(raw) = RawChunk::read(bytes, offset) else {
+            continue;
+        };
+        if raw.crc_valid() {
+            return Some(offset);
+        }
+        if fallback.is_none() && registry::lookup(&raw.type_bytes).is_some() {
+            fallback = Some(offset);
+        }
+    }
+    fallback
+}
+
+// A corrupted length that still fits in the file only holds up if the next chunk lines up
+fn starts_chunk(bytes: &[u8], offset: usize) -> bool {
+    offset == bytes.len()
+        || RawChunk::read(bytes, offset).is_some_and(|raw| raw.chunk_type().is_some())
+}
+
+pub fn repair(bytes: &[u8], keep_trailing: bool) -> crate::Result<Repair> {
+    if bytes.len() < Png::STD_HEADER_LENGTH {
+        return Err("Png bytes length less than 8".into());
+    }
+    let mut fixes = Vec::new();
+    // A missing signature leaves the first chunk at offset 0, a damaged one still takes 8 bytes
+    let mut offset = if bytes.starts_with(&Png::STANDARD_HEADER) {
+        Png::STD_HEADER_LENGTH
+    } else if RawChunk::read(bytes, 0).is_some_and(|raw| raw.crc_valid()) {
+        fixes.push("Added the missing PNG signature".to_string());
+        0
+    } else {
+        fixes.push("Restored the damaged PNG signature".to_string());
+        Png::STD_HEADER_LENGTH
+    };
+
+    let mut chunks = Vec::new();
+    let mut trailing = Vec::new();
+    while offset < bytes.len() {
+        let parsed = RawChunk::read(bytes, offset)
+            .and_then(|raw| Some((raw.chunk_type()?, raw)))
+            .filter(|(chunk_type, raw)| {
+                raw.crc_valid()
+                    || *chunk_type == ChunkType::IEND
+                    || starts_chunk(bytes, offset + raw.len())
+            });
+        let Some((chunk_type, raw)) = parsed else {
+            match resync(bytes, offset + 1) {
+                Some(next) => {
+                    fixes.push(format!(
+                        "Skipped {} corrupted bytes at offset {offset}, resynchronized on {} at offset {next}",
+                        next - offset,
+                        chunk_type::escape(&bytes[next + 4..next + 8].try_into()?)
+                    ));
+                    offset = next;
+                    continue;
+                }
+                None => {
+                    fixes.push(format!(
+                        "Dropped {} bytes of trailing garbage at offset {offset}",
+                        bytes.len() - offset
+                    ));
+                    break;
+                }
+            }
+        };
+
+        let chunk = Chunk::new(chunk_type, raw.data.to_vec());
+        if chunk.crc() != raw.crc {
+            fixes.push(format!(
+                "Recomputed the CRC of chunk {} ({}) at offset {offset}",
+                chunks.len(),
+                chunk.chunk_type()
+            ));
+        }
+        offset += raw.len();
+        let is_end = chunk.chunk_type() == &ChunkType::IEND;
+        chunks.push(chunk);
+        if is_end {
+            // Whatever follows IEND is not image data, it only stays when asked for
+            let after_end = &bytes[offset..];
+            if !after_end.is_empty() && keep_trailing {
+                fixes.push(format!(
+                    "Kept {} bytes of trailing data after IEND",
+                    after_end.len()
+                ));
+                trailing = after_end.to_vec();
+            } else if !after_end.is_empty() {
+                fixes.push(format!(
+                    "Dropped {} bytes of trailing data after IEND",
+                    after_end.len()
+                ));
+            }
+            break;
+        }
+    }
+
+    if chunks.last().map(Chunk::chunk_type) != Some(&ChunkType::IEND) {
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+        fixes.push("Appended the missing IEND chunk".to_string());
+    }
+    let mut png = Png::from_chunks(chunks);
+    png.set_trailing(trailing);
+    Ok(Repair { png, fixes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"secret".to_vec()),
+            Chunk::new(ChunkType::IDAT, vec![1, 2, 3]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_clean_file_is_unchanged() {
+        let bytes = testing_bytes();
+        let repair = repair(&bytes, false).unwrap();
+        assert!(repair.fixes().is_empty());
+        assert_eq!(repair.png().as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_recomputes_crc_and_drops_trailing_data() {
+        let mut bytes = testing_bytes();
+        let crc_offset = 8 + 25 + 8 + 6;
+        bytes[crc_offset] ^= 0xff;
+        bytes.extend(b"garbage");
+        let repair = repair(&bytes, false).unwrap();
+        assert_eq!(repair.fixes().len(), 2);
+        assert!(repair.fixes()[0].contains("CRC of chunk 1 (ruSt)"));
+        assert_eq!(
+            repair.fixes()[1],
+            "Dropped 7 bytes of trailing data after IEND"
+        );
+        assert!(repair.png().trailing().is_empty());
+        assert_eq!(repair.png().as_bytes(), testing_bytes());
+    }
+
+    #[test]
+    fn test_keeps_trailing_data_when_asked() {
+        let mut bytes = testing_bytes();
+        bytes.extend(b"garbage");
+        let repair = repair(&bytes, true).unwrap();
+        assert_eq!(repair.fixes(), ["Kept 7 bytes of trailing data after IEND"]);
+        assert_eq!(repair.png().as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_missing_signature() {
+        let bytes = testing_bytes();
+        let repair = repair(&bytes[Png::STD_HEADER_LENGTH..], false).unwrap();
+        assert_eq!(repair.fixes(), ["Added the missing PNG signature"]);
+        assert_eq!(repair.png().as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_damaged_signature() {
+        let mut bytes = testing_bytes();
+        bytes[1] = b'X';
+        let repair = repair(&bytes, false).unwrap();
+        assert_eq!(repair.fixes(), ["Restored the damaged PNG signature"]);
+        assert_eq!(repair.png().as_bytes(), testing_bytes());
+    }
+
+    #[test]
+    fn test_resynchronizes_after_corruption() {
+        let mut bytes = testing_bytes();
+        // Clobber the length and type of ruSt so the parser loses its place
+        let rust_offset = 8 + 25;
+        bytes[rust_offset..rust_offset + 8].copy_from_slice(&[0xff; 8]);
+        let repair = repair(&bytes, false).unwrap();
+        let types: Vec<String> = repair
+            .png()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        assert!(repair.fixes()[0].contains("resynchronized on IDAT"));
+        assert!(Png::try_from(repair.png().as_bytes().as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_resynchronizes_after_corrupted_length() {
+        let mut bytes = testing_bytes();
+        // Shrink the length of ruSt, it still fits in the file but the CRC no longer matches
+        let rust_offset = 8 + 25;
+        bytes[rust_offset + 3] = 2;
+        let repair = repair(&bytes, false).unwrap();
+        let types: Vec<String> = repair
+            .png()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        assert!(repair.fixes()[0].contains("resynchronized on IDAT"));
+    }
+
+    #[test]
+    fn test_appends_missing_iend_and_drops_truncated_chunk() {
+        let bytes = testing_bytes();
+        let truncated = &bytes[..bytes.len() - 12 - 5];
+        let repair = repair(truncated, false).unwrap();
+        let fixes = repair.fixes();
+        assert_eq!(fixes.len(), 2);
+        assert!(fixes[0].contains("Dropped 10 bytes"));
+        assert_eq!(fixes[1], "Appended the missing IEND chunk");
+        assert_eq!(repair.png().chunks().len(), 3);
+    }
+}
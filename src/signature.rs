@@ -0,0 +1,389 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::crypto::parse_key;
+use crate::png::Png;
+use ed25519_dalek::{Signer, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH, SIGNATURE_LENGTH};
+use rand_core::OsRng;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const SIGNING_KEY_PREFIX: &str = "pngme-signing-key:";
+const VERIFYING_KEY_PREFIX: &str = "pngme-verifying-key:";
+const VERSION: u8 = 1;
+const COVERS_IMAGE: u8 = 1;
+const CONTEXT: &[u8] = b"pngme signature v1";
+
+pub struct SigningKey {
+    key: ed25519_dalek::SigningKey,
+}
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        Self {
+            key: ed25519_dalek::SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            key: self.key.verifying_key(),
+        }
+    }
+}
+
+impl FromStr for SigningKey {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes: [u8; SECRET_KEY_LENGTH] = parse_key(s, SIGNING_KEY_PREFIX)?;
+        Ok(Self {
+            key: ed25519_dalek::SigningKey::from_bytes(&bytes),
+        })
+    }
+}
+
+impl Display for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{SIGNING_KEY_PREFIX}{}",
+            hex::encode(self.key.to_bytes())
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct VerifyingKey {
+    key: ed25519_dalek::VerifyingKey,
+}
+
+impl VerifyingKey {
+    fn from_bytes(bytes: &[u8; PUBLIC_KEY_LENGTH]) -> crate::Result<Self> {
+        Ok(Self {
+            key: ed25519_dalek::VerifyingKey::from_bytes(bytes)?,
+        })
+    }
+}
+
+impl FromStr for VerifyingKey {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&parse_key(s, VERIFYING_KEY_PREFIX)?)
+    }
+}
+
+impl Display for VerifyingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{VERIFYING_KEY_PREFIX}{}",
+            hex::encode(self.key.as_bytes())
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Verification {
+    Valid(VerifyingKey),
+    // The signature matches the key embedded next to it, which anyone can replace
+    Unverified(VerifyingKey),
+    Invalid(String),
+    Missing,
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valid(key) => write!(f, "valid, signed by {key}"),
+            Self::Unverified(key) => write!(
+                f,
+                "UNVERIFIED: untrusted signature by {key}, pass a trusted --key to verify it"
+            ),
+            Self::Invalid(reason) => write!(f, "INVALID: {reason}"),
+            Self::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Signature {
+    target: ChunkType,
+    covers_image: bool,
+    key: VerifyingKey,
+    signature: ed25519_dalek::Signature,
+}
+
+impl Signature {
+    // Ancillary, private and unsafe to copy, since it may cover the image data
+    pub const CHUNK_TYPE: &'static str = "siGN";
+
+    pub fn sign(
+        png: &Png,
+        target: &ChunkType,
+        signing_key: &SigningKey,
+        covers_image: bool,
+    ) -> crate::Result<Self> {
+        let message = signed_message(png, target, covers_image)?;
+        Ok(Self {
+            target: target.clone(),
+            covers_image,
+            key: signing_key.verifying_key(),
+            signature: signing_key.key.sign(&message),
+        })
+    }
+
+    pub fn target(&self) -> &ChunkType {
+        &self.target
+    }
+
+    pub fn covers_image(&self) -> bool {
+        self.covers_image
+    }
+
+    pub fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    // Only proves the data wasn't changed after signing, not who signed it
+    pub fn check_integrity(&self, png: &Png) -> Verification {
+        let message = match signed_message(png, &self.target, self.covers_image) {
+            Ok(message) => message,
+            Err(e) => return Verification::Invalid(e.to_string()),
+        };
+        match self.key.key.verify_strict(&message, &self.signature) {
+            Ok(()) => Verification::Unverified(self.key),
+            Err(_) => Verification::Invalid("signature does not match the signed data".into()),
+        }
+    }
+
+    pub fn verify(&self, png: &Png, trusted: &VerifyingKey) -> Verification {
+        if &self.key != trusted {
+            return Verification::Invalid(format!("signed by untrusted key {}", self.key));
+        }
+        match self.check_integrity(png) {
+            Verification::Unverified(key) => Verification::Valid(key),
+            result => result,
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let flags = if self.covers_image { COVERS_IMAGE } else { 0 };
+        let mut data = vec![VERSION, flags];
+        data.extend(self.target.bytes());
+        data.extend(self.key.key.as_bytes());
+        data.extend(self.signature.to_bytes());
+        Chunk::new(ChunkType::SIGN, data)
+    }
+}
+
+impl TryFrom<&Chunk> for Signature {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        chunk.expect_type(Self::CHUNK_TYPE)?;
+        chunk.expect_len(2 + 4 + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH)?;
+        let data = chunk.data();
+        if data[0] != VERSION {
+            return Err(format!("Unsupported signature version: {}", data[0]).into());
+        }
+
+        let target: [u8; 4] = data[2..6].try_into()?;
+        let key: [u8; PUBLIC_KEY_LENGTH] = data[6..6 + PUBLIC_KEY_LENGTH].try_into()?;
+        let signature: [u8; SIGNATURE_LENGTH] = data[6 + PUBLIC_KEY_LENGTH..].try_into()?;
+        Ok(Self {
+            target: ChunkType::try_from(target)?,
+            covers_image: data[1] & COVERS_IMAGE != 0,
+            key: VerifyingKey::from_bytes(&key)?,
+            signature: ed25519_dalek::Signature::from_bytes(&signature),
+        })
+    }
+}
+
+pub fn signatures(png: &Png) -> Vec<crate::Result<Signature>> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| chunk.chunk_type().to_string() == Signature::CHUNK_TYPE)
+        .map(Signature::try_from)
+        .collect()
+}
+
+// Without a trusted key the best possible outcome is Unverified, the embedded key proves nothing
+pub fn verify(png: &Png, target: &ChunkType, trusted: Option<&VerifyingKey>) -> Verification {
+    let mut signers = Vec::new();
+    for signature in signatures(png) {
+        match signature {
+            Ok(signature) if signature.target() == target => {
+                // Any invalid signature on the chunk taints it, even next to a valid one
+                match signature.check_integrity(png) {
+                    Verification::Unverified(key) => signers.push(key),
+                    result => return result,
+                }
+            }
+            Ok(_) => {}
+            Err(e) => return Verification::Invalid(e.to_string()),
+        }
+    }
+    match (signers.first(), trusted) {
+        (None, _) => Verification::Missing,
+        (Some(_), Some(trusted)) if signers.contains(trusted) => Verification::Valid(*trusted),
+        (Some(key), Some(_)) => Verification::Invalid(format!("signed by untrusted key {key}")),
+        (Some(key), None) => Verification::Unverified(*key),
+    }
+}
+
+// Chunks are framed with their length and type so the signed bytes are unambiguous.
+// Every chunk of the target type is covered, payloads may be split across several.
+fn signed_message(png: &Png, target: &ChunkType, covers_image: bool) -> crate::Result<Vec<u8>> {
+    if png.chunk_by_type(&target.to_string()).is_none() {
+        return Err(format!("signed chunk {target} not present").into());
+    }
+    let mut message = CONTEXT.to_vec();
+    message.push(if covers_image { COVERS_IMAGE } else { 0 });
+    let mut push_chunk = |chunk: &Chunk| {
+        message.extend(chunk.length().to_be_bytes());
+        message.extend(chunk.chunk_type().bytes());
+        message.extend(chunk.data());
+    };
+    for chunk in png.chunks() {
+        if chunk.chunk_type() == target {
+            push_chunk(chunk);
+        }
+    }
+    if covers_image {
+        for chunk in png.chunks() {
+            let chunk_type = chunk.chunk_type().to_string();
+            if chunk_type == "IHDR" || chunk_type == "IDAT" {
+                push_chunk(chunk);
+            }
+        }
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", &[1, 2, 3]),
+            chunk("ruSt", b"This is where your secret message will be!"),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    fn signed_png(covers_image: bool) -> (Png, SigningKey) {
+        let mut png = testing_png();
+        let signing_key = SigningKey::generate();
+        let target = ChunkType::from_str("ruSt").unwrap();
+        let signature = Signature::sign(&png, &target, &signing_key, covers_image).unwrap();
+        png.append_chunk(signature.to_chunk());
+        (png, signing_key)
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let (png, signing_key) = signed_png(false);
+        let target = ChunkType::from_str("ruSt").unwrap();
+        let key = signing_key.verifying_key();
+        assert_eq!(verify(&png, &target, Some(&key)), Verification::Valid(key));
+    }
+
+    #[test]
+    fn test_signature_without_trusted_key_is_unverified() {
+        let (png, signing_key) = signed_png(false);
+        let target = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(
+            verify(&png, &target, None),
+            Verification::Unverified(signing_key.verifying_key())
+        );
+    }
+
+    #[test]
+    fn test_resigned_with_another_key() {
+        let (png, signing_key) = signed_png(false);
+        let target = ChunkType::from_str("ruSt").unwrap();
+        let mut chunks = png.chunks().to_vec();
+        chunks[2] = chunk("ruSt", b"This is where your secret message won't be!");
+        let mut forged = Png::from_chunks(chunks);
+        forged.retain_chunks(|chunk| chunk.chunk_type().to_string() != Signature::CHUNK_TYPE);
+        let forger = SigningKey::generate();
+        let signature = Signature::sign(&forged, &target, &forger, false).unwrap();
+        forged.append_chunk(signature.to_chunk());
+
+        let trusted = signing_key.verifying_key();
+        assert!(matches!(
+            verify(&forged, &target, Some(&trusted)),
+            Verification::Invalid(_)
+        ));
+        assert!(matches!(
+            signature.verify(&forged, &trusted),
+            Verification::Invalid(_)
+        ));
+        assert_eq!(
+            verify(&forged, &target, None),
+            Verification::Unverified(forger.verifying_key())
+        );
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let (png, _) = signed_png(true);
+        let signature = signatures(&png).pop().unwrap().unwrap();
+        let decoded = Signature::try_from(&signature.to_chunk()).unwrap();
+        assert_eq!(decoded, signature);
+        assert!(decoded.covers_image());
+    }
+
+    #[test]
+    fn test_tampered_message() {
+        let (png, _) = signed_png(false);
+        let mut chunks = png.chunks().to_vec();
+        chunks[2] = chunk("ruSt", b"This is where your secret message won't be!");
+        let png = Png::from_chunks(chunks);
+        let target = ChunkType::from_str("ruSt").unwrap();
+        assert!(matches!(
+            verify(&png, &target, None),
+            Verification::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_image_only_matters_when_covered() {
+        for covers_image in [false, true] {
+            let (png, signing_key) = signed_png(covers_image);
+            let mut chunks = png.chunks().to_vec();
+            chunks[1] = chunk("IDAT", &[3, 2, 1]);
+            let png = Png::from_chunks(chunks);
+            let target = ChunkType::from_str("ruSt").unwrap();
+            let key = signing_key.verifying_key();
+            let valid = matches!(verify(&png, &target, Some(&key)), Verification::Valid(_));
+            assert_eq!(valid, !covers_image);
+        }
+    }
+
+    #[test]
+    fn test_missing_signature() {
+        let target = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(verify(&testing_png(), &target, None), Verification::Missing);
+    }
+
+    #[test]
+    fn test_key_text_round_trip() {
+        let signing_key = SigningKey::generate();
+        let parsed = SigningKey::from_str(&signing_key.to_string()).unwrap();
+        assert_eq!(parsed.verifying_key(), signing_key.verifying_key());
+        let verifying_key = signing_key.verifying_key();
+        assert_eq!(
+            VerifyingKey::from_str(&verifying_key.to_string()).unwrap(),
+            verifying_key
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::chunk::Chunk;
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+// Layout: MAGIC | version | payload id (u32) | index (u32) | total (u32) | part data
+pub const MAGIC: [u8; 4] = *b"PNGS";
+const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + 4 + 4;
+pub const MAX_CHUNK_LEN: usize = Chunk::MAX_LENGTH;
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 1 << 20;
+const MAX_LISTED_MISSING: usize = 5;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Part<'a> {
+    payload_id: u32,
+    index: u32,
+    total: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Part<'a> {
+    pub fn payload_id(&self) -> u32 {
+        self.payload_id
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Part<'a> {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        if !is_part(bytes) || bytes.len() < HEADER_LEN {
+            return Err("Chunk is not part of a split payload".into());
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(format!("Unsupported split payload version: {version}").into());
+        }
+        let field = |idx: usize| {
+            let start = MAGIC.len() + 1 + idx * 4;
+            u32::from_be_bytes([
+                bytes[start],
+                bytes[start + 1],
+                bytes[start + 2],
+                bytes[start + 3],
+            ])
+        };
+        let part = Self {
+            payload_id: field(0),
+            index: field(1),
+            total: field(2),
+            data: &bytes[HEADER_LEN..],
+        };
+        if part.total == 0 || part.index >= part.total {
+            return Err(format!("Invalid part number {} of {}", part.index + 1, part.total).into());
+        }
+        Ok(part)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reassembled {
+    data: Vec<u8>,
+    parts: usize,
+    issues: Vec<String>,
+}
+
+impl Reassembled {
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn parts(&self) -> usize {
+        self.parts
+    }
+
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+}
+
+impl Display for Reassembled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Reassembled {} bytes from {} parts",
+            self.data.len(),
+            self.parts
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "  Warning: {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn is_part(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+pub fn split(payload: &[u8], max_chunk_size: usize) -> crate::Result<Vec<Vec<u8>>> {
+    if max_chunk_size <= HEADER_LEN || max_chunk_size > MAX_CHUNK_LEN {
+        return Err(format!(
+            "Maximum chunk size must be between {} and {MAX_CHUNK_LEN} bytes",
+            HEADER_LEN + 1
+        )
+        .into());
+    }
+    let part_len = max_chunk_size - HEADER_LEN;
+    let total = u32::try_from(payload.len().div_ceil(part_len).max(1))
+        .map_err(|_| "Payload needs too many parts, raise the maximum chunk size")?;
+    let digest = Sha256::digest(payload);
+    let payload_id = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+
+    let mut parts = Vec::with_capacity(total as usize);
+    for index in 0..total {
+        let start = index as usize * part_len;
+        let end = (start + part_len).min(payload.len());
+        let mut part = MAGIC.to_vec();
+        part.push(VERSION);
+        part.extend(payload_id.to_be_bytes());
+        part.extend(index.to_be_bytes());
+        part.extend(total.to_be_bytes());
+        part.extend(&payload[start..end]);
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+// Parts belong to the payload of the first one, parts of other payloads are ignored
+pub fn reassemble(chunks: &[&[u8]]) -> crate::Result<Reassembled> {
+    let mut parts = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        parts.push(Part::try_from(*chunk)?);
+    }
+    let first = parts.first().ok_or("No parts to reassemble")?.clone();
+    let mut issues = Vec::new();
+    let other_payloads = parts
+        .iter()
+        .filter(|part| part.payload_id != first.payload_id)
+        .count();
+    if other_payloads > 0 {
+        issues.push(format!(
+            "{other_payloads} parts belong to another payload and were ignored"
+        ));
+    }
+    parts.retain(|part| part.payload_id == first.payload_id);
+
+    if parts.iter().any(|part| part.total != first.total) {
+        return Err("Parts disagree on the number of parts".into());
+    }
+    if parts.windows(2).any(|pair| pair[0].index > pair[1].index) {
+        issues.push("Parts are out of order, reassembled by sequence number".into());
+        parts.sort_by_key(|part| part.index);
+    }
+    if let Some(pair) = parts.windows(2).find(|pair| pair[0].index == pair[1].index) {
+        return Err(format!("Part {} appears more than once", pair[0].index + 1).into());
+    }
+    // Parts are unique and below total by now, so the count is exact without walking 0..total,
+    // which comes straight from the file and may be u32::MAX
+    let missing = first.total as usize - parts.len();
+    if missing > MAX_LISTED_MISSING {
+        return Err(format!("Missing {missing} of {} parts", first.total).into());
+    }
+    if missing > 0 {
+        let listed: Vec<String> = (0..first.total)
+            .filter(|index| {
+                parts
+                    .binary_search_by_key(index, |part| part.index)
+                    .is_err()
+            })
+            .take(missing)
+            .map(|index| (index + 1).to_string())
+            .collect();
+        return Err(format!("Missing parts {} of {}", listed.join(", "), first.total).into());
+    }
+
+    Ok(Reassembled {
+        data: parts.iter().flat_map(|part| part.data).copied().collect(),
+        parts: parts.len(),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_payload() -> Vec<u8> {
+        (0..=255).cycle().take(1000).collect()
+    }
+
+    #[test]
+    fn test_split_and_reassemble() {
+        let payload = testing_payload();
+        let parts = split(&payload, HEADER_LEN + 300).unwrap();
+        assert_eq!(parts.len(), 4);
+        assert!(parts.iter().all(|part| part.len() <= HEADER_LEN + 300));
+        let parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        let reassembled = reassemble(&parts).unwrap();
+        assert_eq!(reassembled.data(), payload.as_slice());
+        assert!(reassembled.issues().is_empty());
+    }
+
+    #[test]
+    fn test_reordered_parts() {
+        let payload = testing_payload();
+        let parts = split(&payload, HEADER_LEN + 300).unwrap();
+        let mut parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        parts.swap(1, 3);
+        let reassembled = reassemble(&parts).unwrap();
+        assert_eq!(reassembled.data(), payload.as_slice());
+        assert_eq!(reassembled.issues().len(), 1);
+    }
+
+    #[test]
+    fn test_missing_part() {
+        let parts = split(&testing_payload(), HEADER_LEN + 300).unwrap();
+        let mut parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        parts.remove(2);
+        let err = reassemble(&parts).unwrap_err();
+        assert_eq!(err.to_string(), "Missing parts 3 of 4");
+    }
+
+    #[test]
+    fn test_huge_total_is_not_enumerated() {
+        let mut part = split(b"payload", HEADER_LEN + 100).unwrap().remove(0);
+        part[MAGIC.len() + 9..HEADER_LEN].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = reassemble(&[part.as_slice()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Missing {} of {} parts", u32::MAX - 1, u32::MAX)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_part() {
+        let parts = split(&testing_payload(), HEADER_LEN + 300).unwrap();
+        let mut parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        parts.push(parts[0]);
+        assert!(reassemble(&parts).is_err());
+    }
+
+    #[test]
+    fn test_invalid_max_chunk_size() {
+        assert!(split(b"payload", HEADER_LEN).is_err());
+        assert!(split(b"payload", MAX_CHUNK_LEN + 1).is_err());
+        assert_eq!(split(b"", HEADER_LEN + 1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::png::Png;
+use crate::{attachment, compression, crypto};
+use std::fmt::Display;
+
+// Above this many bits per byte the data is most likely compressed or encrypted
+const HIGH_ENTROPY: f64 = 7.5;
+
+pub const FILE_SIGNATURES: [(&[u8], &str); 14] = [
+    (&Png::STANDARD_HEADER, "PNG image"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"PK\x05\x06", "empty ZIP archive"),
+    (b"%PDF-", "PDF document"),
+    (b"\x1f\x8b\x08", "gzip data"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"Rar!\x1a\x07", "RAR archive"),
+    (b"\x7fELF", "ELF executable"),
+    (&crypto::MAGIC, "pngme encrypted payload"),
+    (&compression::MAGIC, "pngme compressed payload"),
+    (&attachment::MAGIC, "pngme attachment"),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignatureMatch {
+    name: &'static str,
+    offset: usize,
+    count: usize,
+}
+
+impl SignatureMatch {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Relative to the start of the scanned data
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trailing {
+    offset: usize,
+    size: usize,
+    entropy: f64,
+    signatures: Vec<SignatureMatch>,
+}
+
+impl Trailing {
+    // None when nothing follows IEND
+    pub fn analyze(png: &Png) -> Option<Self> {
+        let data = png.trailing();
+        if data.is_empty() {
+            return None;
+        }
+        Some(Self {
+            offset: png.trailing_offset(),
+            size: data.len(),
+            entropy: entropy(data),
+            signatures: find_signatures(data),
+        })
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn entropy(&self) -> f64 {
+        self.entropy
+    }
+
+    pub fn signatures(&self) -> &[SignatureMatch] {
+        &self.signatures
+    }
+}
+
+impl Display for Trailing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} bytes after IEND at offset {}",
+            self.size, self.offset
+        )?;
+        write!(f, "Entropy: {:.2} bits per byte", self.entropy)?;
+        if self.entropy >= HIGH_ENTROPY {
+            write!(f, " (likely compressed or encrypted)")?;
+        }
+        writeln!(f)?;
+        if self.signatures.is_empty() {
+            return writeln!(f, "No known file signatures");
+        }
+        writeln!(f, "File signatures:")?;
+        for signature in &self.signatures {
+            write!(
+                f,
+                "  {} at offset {}",
+                signature.name,
+                self.offset + signature.offset
+            )?;
+            if signature.count > 1 {
+                write!(f, " ({} matches)", signature.count)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Shannon entropy in bits per byte, from 0 for constant data up to 8 for uniform noise
+pub fn entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Reports each kind once at its first offset, archives repeat their headers for every entry
+pub fn find_signatures(data: &[u8]) -> Vec<SignatureMatch> {
+    let mut matches: Vec<SignatureMatch> = Vec::new();
+    for offset in 0..data.len() {
+        for (magic, name) in FILE_SIGNATURES {
+            if !data[offset..].starts_with(magic) {
+                continue;
+            }
+            match matches.iter_mut().find(|found| found.name == name) {
+                Some(found) => found.count += 1,
+                None => matches.push(SignatureMatch {
+                    name,
+                    offset,
+                    count: 1,
+                }),
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+
+    fn testing_png(trailing: &[u8]) -> Png {
+        let mut bytes = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+        .as_bytes();
+        bytes.extend(trailing);
+        Png::try_from(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&[7; 100]), 0.0);
+        assert_eq!(entropy(&[0, 1]), 1.0);
+        let uniform: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&uniform), 8.0);
+    }
+
+    #[test]
+    fn test_analyze_finds_signatures() {
+        let mut trailing = b"junk%PDF-1.7 ".to_vec();
+        trailing.extend(b"PK\x03\x04aPK\x03\x04b");
+        trailing.extend(Png::STANDARD_HEADER);
+        let analysis = Trailing::analyze(&testing_png(&trailing)).unwrap();
+        assert_eq!(analysis.offset(), 8 + 25 + 12);
+        assert_eq!(analysis.size(), trailing.len());
+        let names: Vec<&str> = analysis.signatures().iter().map(|m| m.name()).collect();
+        assert_eq!(names, ["PDF document", "ZIP archive", "PNG image"]);
+        assert_eq!(analysis.signatures()[0].offset(), 4);
+        assert_eq!(analysis.signatures()[1].count(), 2);
+    }
+
+    #[test]
+    fn test_no_trailing_data() {
+        assert!(Trailing::analyze(&testing_png(&[])).is_none());
+    }
+}
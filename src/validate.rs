@@ -0,0 +1,131 @@
+use crate::apng::Animation;
+use crate::color::ColorSpace;
+use crate::ihdr::ImageHeader;
+use crate::palette::PaletteInfo;
+use crate::png::Png;
+use crate::registry;
+use crate::signature::{self, Verification};
+use crate::trailing::Trailing;
+use std::collections::HashSet;
+
+// Collects every problem rather than stopping at the first, like the typed chunk reports
+pub fn validate(png: &Png) -> Vec<String> {
+    let mut issues = Vec::new();
+    let types: Vec<String> = png
+        .chunks()
+        .iter()
+        .map(|chunk| chunk.chunk_type().to_string())
+        .collect();
+    let count = |chunk_type: &str| types.iter().filter(|t| *t == chunk_type).count();
+
+    if let Err(e) = ImageHeader::from_png(png) {
+        issues.push(e.to_string());
+    }
+    if count("IEND") == 0 {
+        issues.push("IEND chunk is missing".to_string());
+    }
+    if let Some(trailing) = Trailing::analyze(png) {
+        issues.push(format!(
+            "{} bytes of data after IEND at offset {}",
+            trailing.size(),
+            trailing.offset()
+        ));
+    }
+    issues.extend(registry::ordering_issues(png));
+    let idat: Vec<usize> = (0..types.len())
+        .filter(|idx| types[*idx] == "IDAT")
+        .collect();
+    match (idat.first(), idat.last()) {
+        (Some(first), Some(last)) if last - first + 1 != idat.len() => {
+            issues.push("IDAT chunks are not consecutive".to_string())
+        }
+        (None, _) => issues.push("No IDAT chunk".to_string()),
+        _ => {}
+    }
+
+    for (idx, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        if !chunk_type.is_reserved_bit_valid() {
+            issues.push(format!(
+                "Chunk {idx} ({chunk_type}) has the reserved bit set"
+            ));
+        }
+        if chunk_type.is_critical() && chunk_type.known_info().is_none() {
+            issues.push(format!(
+                "Chunk {idx} ({chunk_type}) is an unknown critical chunk"
+            ));
+        }
+    }
+
+    match PaletteInfo::from_png(png) {
+        Ok(palette_info) => issues.extend(palette_info.issues().iter().cloned()),
+        Err(e) => issues.push(e.to_string()),
+    }
+    issues.extend(ColorSpace::from_png(png).conflicts().iter().cloned());
+    if Animation::is_animated(png) {
+        match Animation::from_png(png) {
+            Ok(animation) => issues.extend(animation.issues().iter().cloned()),
+            Err(e) => issues.push(e.to_string()),
+        }
+    }
+    for signature in signature::signatures(png) {
+        let result = match signature {
+            Ok(signature) => match signature.check_integrity(png) {
+                Verification::Unverified(_) => continue,
+                result => format!("{}: {result}", signature.target()),
+            },
+            Err(e) => e.to_string(),
+        };
+        issues.push(format!("Signature {result}"));
+    }
+
+    // Several checks can spot the same problem, report it once
+    let mut seen = HashSet::new();
+    issues.retain(|issue| seen.insert(issue.clone()));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::ihdr::ColorType;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn header() -> Chunk {
+        ImageHeader::new(1, 1, 8, ColorType::Rgb, false)
+            .unwrap()
+            .to_chunk()
+    }
+
+    #[test]
+    fn test_valid_png() {
+        let png = Png::from_chunks(vec![
+            header(),
+            chunk("IDAT", &[1]),
+            chunk("ruSt", b"hi"),
+            chunk("IEND", &[]),
+        ]);
+        assert!(validate(&png).is_empty());
+    }
+
+    #[test]
+    fn test_structural_issues() {
+        let png = Png::from_chunks(vec![
+            chunk("IDAT", &[1]),
+            header(),
+            chunk("IDAT", &[2]),
+            chunk("RuSt", b"hi"),
+        ]);
+        let issues = validate(&png);
+        assert!(issues.contains(&"IHDR must be the first chunk".to_string()));
+        assert!(issues.contains(&"IEND chunk is missing".to_string()));
+        assert!(issues.contains(&"IDAT chunks are not consecutive".to_string()));
+        assert!(issues.contains(&"Chunk 3 (RuSt) is an unknown critical chunk".to_string()));
+    }
+}